@@ -0,0 +1,124 @@
+//! Embeds `static/` into the binary, precompressed with gzip and brotli,
+//! see `src/web.rs`.
+
+use sha2::{Digest, Sha256};
+use std::fmt::Write as _;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+const STATIC_DIR: &str = "static";
+/// Compressed copies are only kept if they save at least this much.
+const MIN_SAVING: f64 = 0.1;
+
+fn main() {
+    println!("cargo:rerun-if-changed={}", STATIC_DIR);
+    let out_dir = PathBuf::from(std::env::var("OUT_DIR").unwrap());
+    let mut files = vec![];
+    collect(Path::new(STATIC_DIR), &mut files);
+    files.sort();
+
+    let mut table = String::from("static ASSETS: &[Asset] = &[\n");
+    for (index, file) in files.iter().enumerate() {
+        let content = fs::read(file).unwrap();
+        let path = file
+            .strip_prefix(STATIC_DIR)
+            .unwrap()
+            .to_string_lossy()
+            .replace('\\', "/");
+        let content_type = content_type(file);
+        let hash = format!("{:x}", Sha256::digest(&content));
+        let compressible = !content_type.starts_with("image/png");
+        let gzip = compressible
+            .then(|| gzip(&content))
+            .and_then(|compressed| store(&out_dir, index, "gz", &content, compressed));
+        let brotli = compressible
+            .then(|| brotli(&content))
+            .and_then(|compressed| store(&out_dir, index, "br", &content, compressed));
+        writeln!(
+            table,
+            "    Asset {{ path: {:?}, content_type: {:?}, etag: {:?}, raw: include_bytes!({:?}), gzip: {}, brotli: {} }},",
+            path,
+            content_type,
+            format!("\"{}\"", &hash[..16]),
+            fs::canonicalize(file).unwrap(),
+            include(gzip),
+            include(brotli),
+        )
+        .unwrap();
+    }
+    table.push_str("];\n");
+    fs::write(out_dir.join("assets.rs"), table).unwrap();
+}
+
+fn collect(dir: &Path, files: &mut Vec<PathBuf>) {
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().starts_with('.'))
+        {
+            continue;
+        }
+        if path.is_dir() {
+            collect(&path, files);
+        } else {
+            files.push(path);
+        }
+    }
+}
+
+fn content_type(path: &Path) -> &'static str {
+    match path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default()
+    {
+        "html" => "text/html; charset=utf-8",
+        "js" => "text/javascript; charset=utf-8",
+        "css" => "text/css; charset=utf-8",
+        "json" => "application/json",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "ico" => "image/x-icon",
+        _ => "text/plain; charset=utf-8",
+    }
+}
+
+fn gzip(content: &[u8]) -> Vec<u8> {
+    let mut encoder = flate2::write::GzEncoder::new(vec![], flate2::Compression::best());
+    encoder.write_all(content).unwrap();
+    encoder.finish().unwrap()
+}
+
+fn brotli(content: &[u8]) -> Vec<u8> {
+    let mut compressed = vec![];
+    let params = brotli::enc::BrotliEncoderParams {
+        quality: 11,
+        ..Default::default()
+    };
+    brotli::BrotliCompress(&mut &content[..], &mut compressed, &params).unwrap();
+    compressed
+}
+
+fn store(
+    out_dir: &Path,
+    index: usize,
+    extension: &str,
+    content: &[u8],
+    compressed: Vec<u8>,
+) -> Option<PathBuf> {
+    if compressed.len() as f64 > content.len() as f64 * (1.0 - MIN_SAVING) {
+        return None;
+    }
+    let path = out_dir.join(format!("asset-{}.{}", index, extension));
+    fs::write(&path, compressed).unwrap();
+    Some(path)
+}
+
+fn include(path: Option<PathBuf>) -> String {
+    match path {
+        Some(path) => format!("Some(include_bytes!({:?}))", path),
+        None => "None".to_string(),
+    }
+}
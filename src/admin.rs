@@ -0,0 +1,592 @@
+//! `rustyplayer admin`, maintenance of the store in `RESOURCE_PATH` while the service is stopped.
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::de::DeserializeOwned;
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Component, Path, PathBuf};
+
+use crate::cli::{AdminCommand, AdminScheduleCommand};
+use crate::consts::{MEDIA_PATH, RESOURCE_PATH};
+use crate::library;
+use crate::models::{Activity, MediaFile, Schedule, State};
+use crate::output::print_schedule;
+use crate::schedule_spec::ScheduleDefinition;
+use crate::utils::{lock_store, write_media_files, write_schedules};
+
+const MEDIA_FILE: &str = "media.json";
+const SCHEDULE_FILE: &str = "schedules.json";
+
+/// Something wrong with the store, and what `check --repair` does about it.
+struct Problem {
+    issue: String,
+    fix: &'static str,
+}
+
+/// Runs an admin command and exits with status 1 if it fails.
+pub fn run(command: AdminCommand) {
+    let result = match command {
+        AdminCommand::Check { repair } => check(repair),
+        AdminCommand::Schedules(AdminScheduleCommand::List) => list_schedules(),
+        AdminCommand::Schedules(AdminScheduleCommand::Deactivate { ids, all }) => {
+            deactivate(ids, all)
+        }
+        AdminCommand::Reindex { dry_run } => reindex(dry_run),
+        AdminCommand::Compact => compact(),
+        AdminCommand::Export {
+            path,
+            without_media,
+        } => export(&path, !without_media),
+        AdminCommand::Import { path } => import(&path),
+    };
+    if let Err(e) = result {
+        eprintln!("error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+/// Fails while the service runs, the lock is released when the file is dropped.
+fn lock() -> Result<File, String> {
+    lock_store().map_err(|e| match e.kind() {
+        io::ErrorKind::WouldBlock => "the store is in use, stop the service first".to_string(),
+        _ => format!("could not lock the store: {}", e),
+    })
+}
+
+fn read<T: DeserializeOwned>(name: &str) -> Result<Vec<T>, String> {
+    let path = Path::new(RESOURCE_PATH).join(name);
+    let content = fs::read_to_string(&path)
+        .map_err(|e| format!("could not read {}: {}", path.display(), e))?;
+    serde_json::from_str(&content).map_err(|e| {
+        format!(
+            "{} is invalid, `admin check --repair` recovers it: {}",
+            path.display(),
+            e
+        )
+    })
+}
+
+fn load() -> Result<State, String> {
+    Ok(State::new(read(MEDIA_FILE)?, read(SCHEDULE_FILE)?))
+}
+
+/// Entries of a store file that can still be read, and whether the file was intact.
+fn salvage<T: DeserializeOwned>(path: &Path, problems: &mut Vec<Problem>) -> (Vec<T>, bool) {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) => {
+            problems.push(Problem {
+                issue: format!("could not read {}: {}", path.display(), e),
+                fix: "start over empty",
+            });
+            return (vec![], false);
+        }
+    };
+    let e = match serde_json::from_str(&content) {
+        Ok(entries) => return (entries, true),
+        Err(e) => e,
+    };
+    let Ok(entries) = serde_json::from_str::<Vec<serde_json::Value>>(&content) else {
+        problems.push(Problem {
+            issue: format!("{} is unreadable: {}", path.display(), e),
+            fix: "start over empty",
+        });
+        return (vec![], false);
+    };
+    let entries = entries
+        .into_iter()
+        .enumerate()
+        .filter_map(|(index, entry)| {
+            serde_json::from_value(entry)
+                .map_err(|e| {
+                    problems.push(Problem {
+                        issue: format!("entry {} of {} is invalid: {}", index, path.display(), e),
+                        fix: "drop the entry",
+                    })
+                })
+                .ok()
+        })
+        .collect();
+    (entries, false)
+}
+
+/// Gives every id after the first use of it a new one.
+fn renumber_duplicates<T>(
+    items: &mut [T],
+    id: impl Fn(&mut T) -> &mut u32,
+    kind: &str,
+    problems: &mut Vec<Problem>,
+) {
+    let mut next = items
+        .iter_mut()
+        .map(|item| *id(item) + 1)
+        .max()
+        .unwrap_or(0);
+    let mut seen = HashSet::new();
+    for item in items.iter_mut() {
+        let id = id(item);
+        if !seen.insert(*id) {
+            problems.push(Problem {
+                issue: format!("{} id {} is used more than once", kind, id),
+                fix: "give it a new id",
+            });
+            *id = next;
+            next += 1;
+        }
+    }
+}
+
+fn check(repair: bool) -> Result<(), String> {
+    let _lock = repair.then(lock).transpose()?;
+    let mut problems = vec![];
+    let resource = Path::new(RESOURCE_PATH);
+    let (mut files, files_intact) = salvage::<MediaFile>(&resource.join(MEDIA_FILE), &mut problems);
+    let (mut schedules, schedules_intact) =
+        salvage::<Schedule>(&resource.join(SCHEDULE_FILE), &mut problems);
+    renumber_duplicates(&mut files, |f| &mut f.id, "file", &mut problems);
+    renumber_duplicates(&mut schedules, |s| &mut s.id, "schedule", &mut problems);
+
+    let scan =
+        library::scan(&files).map_err(|e| format!("could not scan {}: {}", MEDIA_PATH, e))?;
+    for file in files.iter().filter(|f| scan.missing.contains(&f.id)) {
+        problems.push(Problem {
+            issue: format!("file {} ({}) is missing: {}", file.id, file.name, file.path),
+            fix: "remove it",
+        });
+    }
+    files.retain(|f| !scan.missing.contains(&f.id));
+
+    let ids: HashSet<u32> = files.iter().map(|f| f.id).collect();
+    for file in files.iter_mut() {
+        if file.source_id.is_some_and(|id| !ids.contains(&id)) {
+            problems.push(Problem {
+                issue: format!("file {} is a version of a missing file", file.id),
+                fix: "forget the original",
+            });
+            file.source_id = None;
+        }
+    }
+    schedules.retain(|schedule| {
+        let exists = ids.contains(&schedule.file_id);
+        if !exists {
+            problems.push(Problem {
+                issue: format!(
+                    "schedule {} plays missing file {}",
+                    schedule.id, schedule.file_id
+                ),
+                fix: "delete the schedule",
+            });
+        }
+        exists
+    });
+    for schedule in schedules.iter_mut() {
+        for (roll, name) in [
+            (&mut schedule.pre_roll, "pre-roll"),
+            (&mut schedule.post_roll, "post-roll"),
+        ] {
+            if roll.is_some_and(|id| !ids.contains(&id)) {
+                problems.push(Problem {
+                    issue: format!(
+                        "schedule {} has missing file {} as {}",
+                        schedule.id,
+                        roll.unwrap(),
+                        name
+                    ),
+                    fix: "clear it",
+                });
+                *roll = None;
+            }
+        }
+        if let Err(e) = ScheduleDefinition::Cron(schedule.schedule.clone()).compile() {
+            problems.push(Problem {
+                issue: format!("schedule {}: {}", schedule.id, e),
+                fix: "deactivate the schedule",
+            });
+            schedule.activity = Activity::Inactive;
+        }
+    }
+
+    for problem in &problems {
+        println!("{} [{}]", problem.issue, problem.fix);
+    }
+    if !scan.untracked.is_empty() {
+        println!(
+            "{} files in {} aren't in the store, `admin reindex` adds them",
+            scan.untracked.len(),
+            MEDIA_PATH
+        );
+    }
+    if problems.is_empty() {
+        println!("No problems found");
+        return Ok(());
+    }
+    if !repair {
+        return Err(format!(
+            "{} problems found, `admin check --repair` applies the fixes in brackets",
+            problems.len()
+        ));
+    }
+    for (name, intact) in [
+        (MEDIA_FILE, files_intact),
+        (SCHEDULE_FILE, schedules_intact),
+    ] {
+        let path = resource.join(name);
+        if !intact && path.exists() {
+            let copy = path.with_extension("json.corrupt");
+            fs::copy(&path, &copy).map_err(|e| format!("could not keep a copy: {}", e))?;
+            println!("Kept the previous {} as {}", name, copy.display());
+        }
+    }
+    write_media_files(&files);
+    write_schedules(&schedules);
+    println!("Repaired {} problems", problems.len());
+    Ok(())
+}
+
+fn list_schedules() -> Result<(), String> {
+    let state = load()?;
+    state.schedules.iter().for_each(print_schedule);
+    Ok(())
+}
+
+fn deactivate(ids: Vec<u32>, all: bool) -> Result<(), String> {
+    let _lock = lock()?;
+    let mut state = load()?;
+    let ids = match all {
+        true => state.schedules.iter().map(|s| s.id).collect(),
+        false => ids,
+    };
+    for id in ids {
+        let schedule = state
+            .get_mut_schedule(id)
+            .ok_or_else(|| format!("schedule {} not found", id))?;
+        schedule.activity = Activity::Inactive;
+        println!("Deactivated schedule {}", id);
+    }
+    state.save_schedules();
+    Ok(())
+}
+
+fn reindex(dry_run: bool) -> Result<(), String> {
+    let _lock = (!dry_run).then(lock).transpose()?;
+    let mut state = load()?;
+    let scan =
+        library::scan(&state.files).map_err(|e| format!("could not scan {}: {}", MEDIA_PATH, e))?;
+    for path in &scan.untracked {
+        match library::adopt(path) {
+            Ok(file) if dry_run => println!("Would add {} as {}", path.display(), file.name),
+            Ok(file) => {
+                let file = state.add_media(file.name, file.path, file.hash, Some(file.metadata));
+                println!("Added {} as {} ({})", path.display(), file.id, file.name);
+            }
+            Err(e) => println!("Skipped {}: {}", path.display(), e),
+        }
+    }
+    if scan.untracked.is_empty() {
+        println!("Every file in {} is in the store", MEDIA_PATH);
+    }
+    if !scan.missing.is_empty() {
+        println!(
+            "{} stored files are missing, `admin check --repair` removes them",
+            scan.missing.len()
+        );
+    }
+    Ok(())
+}
+
+fn compact() -> Result<(), String> {
+    let _lock = lock()?;
+    let mut state = load()?;
+    let (files, schedules) = renumber(&mut state)?;
+    state.flush();
+    println!("Renumbered {} files and {} schedules", files, schedules);
+    Ok(())
+}
+
+/// Numbers files and schedules from zero without gaps, returns how many of each there are.
+fn renumber(state: &mut State) -> Result<(usize, usize), String> {
+    let file_ids = new_ids(state.files.iter().map(|f| f.id));
+    let schedule_ids = new_ids(state.schedules.iter().map(|s| s.id));
+    let file_id = |id: u32, schedule: u32| {
+        file_ids.get(&id).copied().ok_or_else(|| {
+            format!(
+                "schedule {} refers to missing file {}, run `admin check --repair` first",
+                schedule, id
+            )
+        })
+    };
+    for schedule in state.schedules.iter_mut() {
+        schedule.file_id = file_id(schedule.file_id, schedule.id)?;
+        schedule.pre_roll = schedule
+            .pre_roll
+            .map(|id| file_id(id, schedule.id))
+            .transpose()?;
+        schedule.post_roll = schedule
+            .post_roll
+            .map(|id| file_id(id, schedule.id))
+            .transpose()?;
+        schedule.id = schedule_ids[&schedule.id];
+    }
+    for file in state.files.iter_mut() {
+        file.source_id = file.source_id.and_then(|id| file_ids.get(&id).copied());
+        file.id = file_ids[&file.id];
+    }
+    Ok((file_ids.len(), schedule_ids.len()))
+}
+
+/// Maps every id to its position among the sorted ids.
+fn new_ids(ids: impl Iterator<Item = u32>) -> HashMap<u32, u32> {
+    let mut ids: Vec<u32> = ids.collect();
+    ids.sort();
+    ids.into_iter().zip(0..).collect()
+}
+
+fn export(path: &Path, with_media: bool) -> Result<(), String> {
+    let write = || -> io::Result<usize> {
+        let encoder = GzEncoder::new(File::create(path)?, Compression::default());
+        let mut archive = tar::Builder::new(encoder);
+        for name in [MEDIA_FILE, SCHEDULE_FILE] {
+            archive.append_path_with_name(
+                Path::new(RESOURCE_PATH).join(name),
+                Path::new("resource").join(name),
+            )?;
+        }
+        let media = match with_media {
+            true => library::media_files()?,
+            false => vec![],
+        };
+        for file in &media {
+            let name = Path::new("media").join(file.strip_prefix(MEDIA_PATH).unwrap());
+            archive.append_path_with_name(file, name)?;
+        }
+        archive.into_inner()?.finish()?;
+        Ok(media.len())
+    };
+    let count = write().map_err(|e| format!("could not export to {}: {}", path.display(), e))?;
+    println!(
+        "Exported the store and {} media files to {}",
+        count,
+        path.display()
+    );
+    Ok(())
+}
+
+/// Where a media file of an archive goes, refusing paths that leave the media directory.
+fn media_target(name: &Path) -> Result<PathBuf, String> {
+    if !name.components().all(|c| matches!(c, Component::Normal(_))) {
+        return Err(format!("invalid path in the archive: {}", name.display()));
+    }
+    Ok(Path::new(MEDIA_PATH).join(name))
+}
+
+/// Replaces the store with the archive's, media files are added next to the existing ones.
+fn import(path: &Path) -> Result<(), String> {
+    let _lock = lock()?;
+    let open = || -> Result<tar::Archive<GzDecoder<File>>, String> {
+        let file =
+            File::open(path).map_err(|e| format!("could not open {}: {}", path.display(), e))?;
+        Ok(tar::Archive::new(GzDecoder::new(file)))
+    };
+    let invalid = |e: io::Error| format!("invalid archive: {}", e);
+
+    // the store is checked before anything is written
+    let mut store = HashMap::new();
+    let mut archive = open()?;
+    for entry in archive.entries().map_err(invalid)? {
+        let mut entry = entry.map_err(invalid)?;
+        let name = entry.path().map_err(invalid)?.into_owned();
+        if let Ok(name) = name.strip_prefix("resource") {
+            let mut content = String::new();
+            entry.read_to_string(&mut content).map_err(invalid)?;
+            store.insert(name.to_path_buf(), content);
+        }
+    }
+    let parse = |name: &str| {
+        store
+            .get(Path::new(name))
+            .ok_or_else(|| format!("the archive contains no {}", name))
+    };
+    let files: Vec<MediaFile> = serde_json::from_str(parse(MEDIA_FILE)?)
+        .map_err(|e| format!("invalid {} in the archive: {}", MEDIA_FILE, e))?;
+    let schedules: Vec<Schedule> = serde_json::from_str(parse(SCHEDULE_FILE)?)
+        .map_err(|e| format!("invalid {} in the archive: {}", SCHEDULE_FILE, e))?;
+
+    let mut count = 0;
+    let mut archive = open()?;
+    for entry in archive.entries().map_err(invalid)? {
+        let mut entry = entry.map_err(invalid)?;
+        let name = entry.path().map_err(invalid)?.into_owned();
+        let Ok(name) = name.strip_prefix("media") else {
+            continue;
+        };
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let target = media_target(name)?;
+        fs::create_dir_all(target.parent().unwrap()).map_err(|e| e.to_string())?;
+        entry
+            .unpack(&target)
+            .map_err(|e| format!("could not write {}: {}", target.display(), e))?;
+        count += 1;
+    }
+    write_media_files(&files);
+    write_schedules(&schedules);
+    println!(
+        "Imported {} files, {} schedules and {} media files",
+        files.len(),
+        schedules.len(),
+        count
+    );
+    let scan =
+        library::scan(&files).map_err(|e| format!("could not scan {}: {}", MEDIA_PATH, e))?;
+    if !scan.missing.is_empty() {
+        println!(
+            "{} stored files are missing, `admin check --repair` removes them",
+            scan.missing.len()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn file(id: u32) -> MediaFile {
+        serde_json::from_value(json!({
+            "id": id,
+            "name": format!("file {}", id),
+            "path": format!("media/{}.mp3", id),
+        }))
+        .unwrap()
+    }
+
+    fn schedule(id: u32, file_id: u32) -> Schedule {
+        Schedule::new(id, file_id, "0 0 8 * * *".to_string(), None)
+    }
+
+    /// Writes `content` to a fresh file, removed along with its directory by the caller.
+    fn store_file(content: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("admin-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(MEDIA_FILE);
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn salvage_reads_intact_files() {
+        let path = store_file(&serde_json::to_string(&[file(0), file(1)]).unwrap());
+        let mut problems = vec![];
+        let (files, intact) = salvage::<MediaFile>(&path, &mut problems);
+        assert!(intact);
+        assert!(problems.is_empty());
+        assert_eq!(files.len(), 2);
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn salvage_drops_invalid_entries() {
+        let path = store_file(
+            r#"[
+                {"id": 0, "name": "a", "path": "media/a.mp3"},
+                {"id": "one", "name": "b"},
+                {"id": 2, "name": "c", "path": "media/c.mp3"}
+            ]"#,
+        );
+        let mut problems = vec![];
+        let (files, intact) = salvage::<MediaFile>(&path, &mut problems);
+        assert!(!intact);
+        assert_eq!(files.iter().map(|f| f.id).collect::<Vec<_>>(), [0, 2]);
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].issue.starts_with("entry 1 of"));
+        assert_eq!(problems[0].fix, "drop the entry");
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn salvage_starts_over_on_unreadable_files() {
+        let path = store_file(r#"[{"id": 0, "name": "#);
+        let mut problems = vec![];
+        let (files, intact) = salvage::<MediaFile>(&path, &mut problems);
+        assert!(!intact);
+        assert!(files.is_empty());
+        assert_eq!(problems[0].fix, "start over empty");
+
+        fs::remove_file(&path).unwrap();
+        let mut problems = vec![];
+        let (files, intact) = salvage::<MediaFile>(&path, &mut problems);
+        assert!(!intact);
+        assert!(files.is_empty());
+        assert!(problems[0].issue.starts_with("could not read"));
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn duplicate_ids_are_renumbered() {
+        let mut schedules = [3, 1, 3, 5, 1].map(|id| schedule(id, 0));
+        let mut problems = vec![];
+        renumber_duplicates(&mut schedules, |s| &mut s.id, "schedule", &mut problems);
+        assert_eq!(schedules.map(|s| s.id), [3, 1, 6, 5, 7]);
+        assert_eq!(problems.len(), 2);
+        assert_eq!(problems[0].issue, "schedule id 3 is used more than once");
+    }
+
+    #[test]
+    fn new_ids_follow_the_sorted_ids() {
+        let ids = new_ids([12, 4, 9].into_iter());
+        assert_eq!(ids, HashMap::from([(4, 0), (9, 1), (12, 2)]));
+    }
+
+    #[test]
+    fn renumbering_remaps_references() {
+        let mut version = file(12);
+        version.source_id = Some(9);
+        let mut announcement = schedule(7, 9);
+        announcement.pre_roll = Some(4);
+        announcement.post_roll = Some(12);
+        let mut state = State::new(
+            vec![file(4), file(9), version],
+            vec![announcement, schedule(2, 4)],
+        );
+
+        assert_eq!(renumber(&mut state), Ok((3, 2)));
+        assert_eq!(
+            state.files.iter().map(|f| f.id).collect::<Vec<_>>(),
+            [0, 1, 2]
+        );
+        assert_eq!(state.files[2].source_id, Some(1));
+        let announcement = &state.schedules[0];
+        assert_eq!(announcement.id, 1);
+        assert_eq!(announcement.file_ids(), [Some(0), Some(1), Some(2)]);
+        assert_eq!(state.schedules[1].id, 0);
+        assert_eq!(state.schedules[1].file_id, 0);
+    }
+
+    #[test]
+    fn renumbering_refuses_missing_files() {
+        let mut state = State::new(vec![file(1)], vec![schedule(0, 5)]);
+        let error = renumber(&mut state).unwrap_err();
+        assert!(error.contains("missing file 5"), "{}", error);
+    }
+
+    #[test]
+    fn import_paths_stay_in_the_media_directory() {
+        assert_eq!(
+            media_target(Path::new("jingles/chime.mp3")),
+            Ok(Path::new(MEDIA_PATH).join("jingles/chime.mp3"))
+        );
+        for name in [
+            "../resource/media.json",
+            "jingles/../../x.mp3",
+            "/etc/passwd",
+            "./x.mp3",
+        ] {
+            assert!(media_target(Path::new(name)).is_err(), "{}", name);
+        }
+    }
+}
@@ -0,0 +1,337 @@
+use serde::de::DeserializeOwned;
+use std::convert::Infallible;
+use warp::multipart::form;
+use warp::{body, delete, get, patch, path, post, put, Filter, Rejection, Reply};
+
+use crate::dto::{
+    CreateGenerator, CreateStream, ErrorResponse, PlayRequest, ScheduleRequest, TrimRequest,
+    UpdateFile, UpdateSchedule,
+};
+use crate::handlers;
+use crate::models::{Announcement, DeleteOptions, LogQuery, UploadOptions, ZoneOptions};
+use crate::routes::{describe_rejection, with_scheduler, with_state, with_zones};
+use crate::utils::max_upload_size;
+use crate::SchedulerMutex;
+use crate::StateMutex;
+use crate::ZonesMutex;
+
+/// Resource oriented API under `/api/v1`, errors are answered with an `ErrorResponse`.
+pub fn routes(
+    state: StateMutex,
+    zones: ZonesMutex,
+    scheduler: SchedulerMutex,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    let files = get_files(state.clone())
+        .or(create_files(state.clone()))
+        .or(analyze_files(state.clone()))
+        .or(scan_files(state.clone()))
+        .or(get_file(state.clone()))
+        .or(update_file(state.clone()))
+        .or(delete_file(state.clone(), scheduler.clone()))
+        .or(get_file_content(state.clone()))
+        .or(create_trimmed(state.clone()))
+        .or(create_version(state.clone()))
+        .or(get_uploads(state.clone()))
+        .or(create_generator(state.clone()))
+        .or(create_stream(state.clone()))
+        .or(create_announcement(
+            state.clone(),
+            zones.clone(),
+            scheduler.clone(),
+        ))
+        .boxed();
+    let schedules = get_schedules(state.clone())
+        .or(create_schedule(
+            state.clone(),
+            zones.clone(),
+            scheduler.clone(),
+        ))
+        .or(get_schedule(state.clone()))
+        .or(replace_schedule(
+            state.clone(),
+            zones.clone(),
+            scheduler.clone(),
+        ))
+        .or(update_schedule(state.clone(), scheduler.clone()))
+        .or(delete_schedule(state.clone(), scheduler))
+        .boxed();
+    let playback = get_status(zones.clone())
+        .or(get_zones(zones.clone()))
+        .or(get_devices())
+        .or(start_playback(state, zones.clone()))
+        .or(playback_action(zones))
+        .or(get_events())
+        .boxed();
+    path("api")
+        .and(path("v1"))
+        .and(files.or(schedules).or(playback).recover(handle_rejection))
+}
+
+async fn handle_rejection(err: Rejection) -> Result<impl Reply, Infallible> {
+    let (code, message) = describe_rejection(&err);
+    Ok(warp::reply::with_status(
+        warp::reply::json(&ErrorResponse {
+            status: code.as_u16(),
+            message,
+        }),
+        code,
+    ))
+}
+
+fn get_files(state: StateMutex) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    path!("files")
+        .and(get())
+        .and(with_state(state))
+        .and_then(handlers::get_files)
+}
+
+fn create_files(
+    state: StateMutex,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    path!("files")
+        .and(post())
+        .and(warp::query::<UploadOptions>())
+        .and(form().max_length(max_upload_size()))
+        .and(with_state(state))
+        .and_then(handlers::create_files)
+}
+
+fn analyze_files(
+    state: StateMutex,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    path!("files" / "analyze")
+        .and(post())
+        .and(with_state(state))
+        .and_then(handlers::analyze_files)
+}
+
+fn scan_files(
+    state: StateMutex,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    path!("files" / "scan")
+        .and(post())
+        .and(with_state(state))
+        .and_then(handlers::scan_files)
+}
+
+fn get_file(state: StateMutex) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    path!("files" / u32)
+        .and(get())
+        .and(with_state(state))
+        .and_then(handlers::get_file)
+}
+
+fn update_file(
+    state: StateMutex,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    path!("files" / u32)
+        .and(patch())
+        .and(json::<UpdateFile>())
+        .and(with_state(state))
+        .and_then(handlers::update_file)
+}
+
+fn delete_file(
+    state: StateMutex,
+    scheduler: SchedulerMutex,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    path!("files" / u32)
+        .and(delete())
+        .and(warp::query::<DeleteOptions>())
+        .and(with_state(state))
+        .and(with_scheduler(scheduler))
+        .and_then(handlers::remove_file_resource)
+}
+
+fn get_file_content(
+    state: StateMutex,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    path!("files" / u32 / "content")
+        .and(get())
+        .and(with_state(state))
+        .and_then(handlers::download_file)
+}
+
+fn create_trimmed(
+    state: StateMutex,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    path!("files" / u32 / "trim")
+        .and(post())
+        .and(json::<TrimRequest>())
+        .and(with_state(state))
+        .and_then(handlers::create_trimmed)
+}
+
+fn create_version(
+    state: StateMutex,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    path!("files" / u32 / String)
+        .and(post())
+        .and(with_state(state))
+        .and_then(handlers::create_version)
+}
+
+fn get_uploads(
+    state: StateMutex,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    path!("uploads")
+        .and(get())
+        .and(with_state(state))
+        .and_then(handlers::get_uploads)
+}
+
+fn create_generator(
+    state: StateMutex,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    path!("generators")
+        .and(post())
+        .and(json::<CreateGenerator>())
+        .and(with_state(state))
+        .and_then(handlers::create_generator)
+}
+
+fn create_stream(
+    state: StateMutex,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    path!("streams")
+        .and(post())
+        .and(json::<CreateStream>())
+        .and(with_state(state))
+        .and_then(handlers::create_stream)
+}
+
+fn create_announcement(
+    state: StateMutex,
+    zones: ZonesMutex,
+    scheduler: SchedulerMutex,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    path!("announcements")
+        .and(post())
+        .and(json::<Announcement>())
+        .and(with_state(state))
+        .and(with_zones(zones))
+        .and(with_scheduler(scheduler))
+        .and_then(handlers::create_announcement)
+}
+
+fn get_schedules(
+    state: StateMutex,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    path!("schedules")
+        .and(get())
+        .and(with_state(state))
+        .and_then(handlers::get_schedules)
+}
+
+fn create_schedule(
+    state: StateMutex,
+    zones: ZonesMutex,
+    scheduler: SchedulerMutex,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    path!("schedules")
+        .and(post())
+        .and(json::<ScheduleRequest>())
+        .and(with_state(state))
+        .and(with_zones(zones))
+        .and(with_scheduler(scheduler))
+        .and_then(handlers::create_schedule)
+}
+
+fn get_schedule(
+    state: StateMutex,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    path!("schedules" / u32)
+        .and(get())
+        .and(with_state(state))
+        .and_then(handlers::get_schedule)
+}
+
+fn replace_schedule(
+    state: StateMutex,
+    zones: ZonesMutex,
+    scheduler: SchedulerMutex,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    path!("schedules" / u32)
+        .and(put())
+        .and(json::<ScheduleRequest>())
+        .and(with_state(state))
+        .and(with_zones(zones))
+        .and(with_scheduler(scheduler))
+        .and_then(handlers::replace_schedule)
+}
+
+fn update_schedule(
+    state: StateMutex,
+    scheduler: SchedulerMutex,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    path!("schedules" / u32)
+        .and(patch())
+        .and(json::<UpdateSchedule>())
+        .and(with_state(state))
+        .and(with_scheduler(scheduler))
+        .and_then(handlers::update_schedule)
+}
+
+fn delete_schedule(
+    state: StateMutex,
+    scheduler: SchedulerMutex,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    path!("schedules" / u32)
+        .and(delete())
+        .and(with_state(state))
+        .and(with_scheduler(scheduler))
+        .and_then(handlers::delete_schedule)
+}
+
+fn get_status(
+    zones: ZonesMutex,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    path!("status")
+        .and(get())
+        .and(with_zones(zones))
+        .and_then(handlers::get_status)
+}
+
+fn get_zones(zones: ZonesMutex) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    path!("zones")
+        .and(get())
+        .and(with_zones(zones))
+        .and_then(handlers::get_zones)
+}
+
+fn get_devices() -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    path!("devices").and(get()).and_then(handlers::get_devices)
+}
+
+fn get_events() -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    path!("events")
+        .and(get())
+        .and(warp::query::<LogQuery>())
+        .and_then(handlers::get_events)
+}
+
+fn start_playback(
+    state: StateMutex,
+    zones: ZonesMutex,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    path!("playback")
+        .and(post())
+        .and(json::<PlayRequest>())
+        .and(with_state(state))
+        .and(with_zones(zones))
+        .and_then(handlers::start_playback)
+}
+
+fn playback_action(
+    zones: ZonesMutex,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    path!("playback" / String)
+        .and(post())
+        .and(warp::query::<ZoneOptions>())
+        .and(with_zones(zones))
+        .and_then(handlers::playback_action)
+}
+
+fn json<T: DeserializeOwned + Send>() -> impl Filter<Extract = (T,), Error = Rejection> + Clone {
+    body::content_length_limit(1024 * 16).and(body::json())
+}
@@ -0,0 +1,213 @@
+use clap::{Args, Parser, Subcommand};
+use std::path::PathBuf;
+
+use crate::schedule_spec::Weekday;
+
+#[derive(Parser)]
+#[command(version, about = "Plays and schedules audio files")]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Runs the player service, the default without a command
+    Serve,
+    /// Controls a running player through its API
+    Client(Box<ClientArgs>),
+    /// Inspects and repairs the stored files and schedules without the service
+    #[command(subcommand)]
+    Admin(AdminCommand),
+}
+
+#[derive(Args)]
+pub struct ClientArgs {
+    /// Address of the player
+    #[arg(long, env = "RUSTYPLAYER_URL", default_value = "http://127.0.0.1:5001")]
+    pub url: String,
+    /// Prints the API's JSON instead of a summary
+    #[arg(long, global = true)]
+    pub json: bool,
+    #[command(subcommand)]
+    pub command: ClientCommand,
+}
+
+#[derive(Subcommand)]
+pub enum ClientCommand {
+    /// Shows what every zone is playing
+    Status,
+    /// Lists, uploads and deletes media files
+    #[command(subcommand)]
+    Files(FileCommand),
+    /// Plays a file
+    Play {
+        id: u32,
+        #[arg(long)]
+        pre_roll: Option<u32>,
+        #[arg(long)]
+        post_roll: Option<u32>,
+        #[arg(long)]
+        zone: Option<String>,
+    },
+    /// Stops playback
+    Stop(ZoneArg),
+    /// Pauses playback
+    Pause(ZoneArg),
+    /// Resumes paused playback
+    Resume(ZoneArg),
+    /// Lists, adds, edits and activates schedules
+    #[command(subcommand)]
+    Schedules(ScheduleCommand),
+    /// Prints recent log events
+    Events {
+        /// Most verbose level to print
+        #[arg(long, default_value = "info")]
+        level: String,
+        /// Keeps printing new events as they happen
+        #[arg(short, long)]
+        follow: bool,
+    },
+}
+
+#[derive(Args)]
+pub struct ZoneArg {
+    /// Zone to control, the default zone if not set
+    #[arg(long)]
+    pub zone: Option<String>,
+}
+
+#[derive(Subcommand)]
+pub enum FileCommand {
+    List,
+    Show {
+        id: u32,
+    },
+    /// Uploads files, one request per file
+    Upload {
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+        /// What to do when a file with the same content exists, `replace` also matches by name
+        #[arg(long, default_value = "reject", value_parser = ["reject", "replace", "keep_both"])]
+        conflict: String,
+    },
+    /// Deletes a file, refused while schedules use it unless told what to do with them
+    Delete {
+        id: u32,
+        /// Deactivates the schedules playing it and drops it from pre- and post-rolls
+        #[arg(long, conflicts_with_all = ["cascade", "reassign"])]
+        force: bool,
+        /// Deletes the schedules using it
+        #[arg(long, conflicts_with = "reassign")]
+        cascade: bool,
+        /// Moves the schedules using it to another file
+        #[arg(long, value_name = "FILE")]
+        reassign: Option<u32>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ScheduleCommand {
+    List,
+    Show {
+        id: u32,
+    },
+    /// Adds a schedule, active unless `--inactive` is given
+    Add {
+        #[arg(long)]
+        file: u32,
+        #[command(flatten)]
+        options: ScheduleOptions,
+        #[arg(long)]
+        inactive: bool,
+    },
+    /// Changes the given fields of a schedule, keeping the others
+    Edit {
+        id: u32,
+        #[arg(long)]
+        file: Option<u32>,
+        #[command(flatten)]
+        options: ScheduleOptions,
+    },
+    Activate {
+        id: u32,
+    },
+    Deactivate {
+        id: u32,
+    },
+    Delete {
+        id: u32,
+    },
+}
+
+#[derive(Args)]
+pub struct ScheduleOptions {
+    /// Cron expressions separated by `;`, instead of `--at` or `--every`
+    #[arg(long, conflicts_with_all = ["at", "every", "days"])]
+    pub cron: Option<String>,
+    /// Times of day, "HH:MM" or "HH:MM:SS"
+    #[arg(long, value_delimiter = ',')]
+    pub at: Vec<String>,
+    /// Repeats every N minutes between `--from` and `--to`
+    #[arg(long, requires_all = ["from", "to"])]
+    pub every: Option<u32>,
+    #[arg(long, requires = "every")]
+    pub from: Option<String>,
+    #[arg(long, requires = "every")]
+    pub to: Option<String>,
+    /// Days of the week, e.g. "Mon,Fri", every day if not set
+    #[arg(long, value_delimiter = ',', value_parser = parse_weekday)]
+    pub days: Vec<Weekday>,
+    #[arg(long)]
+    pub pre_roll: Option<u32>,
+    #[arg(long)]
+    pub post_roll: Option<u32>,
+    #[arg(long)]
+    pub zone: Option<String>,
+}
+
+#[derive(Subcommand)]
+pub enum AdminCommand {
+    /// Checks the store for problems, exits with status 1 if there are any
+    Check {
+        /// Fixes what it can, keeping a copy of unreadable files
+        #[arg(long)]
+        repair: bool,
+    },
+    /// Lists and deactivates schedules
+    #[command(subcommand)]
+    Schedules(AdminScheduleCommand),
+    /// Adds audio files found in the media directory to the store
+    Reindex {
+        /// Only lists what would be added
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Renumbers files and schedules from 0, keeping their references intact
+    Compact,
+    /// Writes the store and the media directory to a .tar.gz archive
+    Export {
+        path: PathBuf,
+        /// Leaves out the media files
+        #[arg(long)]
+        without_media: bool,
+    },
+    /// Restores an archive written by `export`, replacing the store
+    Import { path: PathBuf },
+}
+
+#[derive(Subcommand)]
+pub enum AdminScheduleCommand {
+    List,
+    Deactivate {
+        #[arg(required_unless_present = "all")]
+        ids: Vec<u32>,
+        #[arg(long, conflicts_with = "ids")]
+        all: bool,
+    },
+}
+
+fn parse_weekday(day: &str) -> Result<Weekday, String> {
+    serde_json::from_value(serde_json::Value::String(day.to_string()))
+        .map_err(|_| format!("unknown day {}, expected Mon to Sun", day))
+}
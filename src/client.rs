@@ -0,0 +1,318 @@
+//! `rustyplayer client`, a command-line client for the `/api/v1` API of a running player.
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::cli::{ClientArgs, ClientCommand, FileCommand, ScheduleCommand, ScheduleOptions};
+use crate::dto::{ErrorResponse, PlayRequest, ScheduleRequest, UpdateSchedule};
+use crate::models::{Activity, LogEntry, MediaFile, Schedule, StatusReport};
+use crate::output::{print_event, print_file, print_schedule, print_status};
+use crate::schedule_spec::{Interval, ScheduleDefinition, ScheduleSpec};
+use crate::utils::percent_encode;
+
+/// How often `events --follow` asks for new events.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+struct Client {
+    url: String,
+    agent: ureq::Agent,
+    json: bool,
+}
+
+/// Runs a client command and exits with status 1 if it fails.
+pub fn run(args: Box<ClientArgs>) {
+    let client = Client {
+        url: args.url.trim_end_matches('/').to_string(),
+        agent: ureq::AgentBuilder::new()
+            .timeout_connect(Duration::from_secs(5))
+            .build(),
+        json: args.json,
+    };
+    if let Err(e) = client.execute(args.command) {
+        eprintln!("error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+impl Client {
+    fn execute(&self, command: ClientCommand) -> Result<(), String> {
+        match command {
+            ClientCommand::Status => {
+                let status: StatusReport = self.get("/api/v1/status")?;
+                self.print(&status, print_status)
+            }
+            ClientCommand::Files(command) => self.files(command),
+            ClientCommand::Play {
+                id,
+                pre_roll,
+                post_roll,
+                zone,
+            } => {
+                let request = PlayRequest {
+                    file_id: id,
+                    pre_roll,
+                    post_roll,
+                    zone,
+                };
+                self.send("POST", "/api/v1/playback", Some(&request))
+            }
+            ClientCommand::Stop(zone) => self.control("stop", zone.zone),
+            ClientCommand::Pause(zone) => self.control("pause", zone.zone),
+            ClientCommand::Resume(zone) => self.control("resume", zone.zone),
+            ClientCommand::Schedules(command) => self.schedules(command),
+            ClientCommand::Events { level, follow } => self.events(&level, follow),
+        }
+    }
+
+    fn files(&self, command: FileCommand) -> Result<(), String> {
+        match command {
+            FileCommand::List => {
+                let files: Vec<MediaFile> = self.get("/api/v1/files")?;
+                self.print(&files, |files| files.iter().for_each(print_file))
+            }
+            FileCommand::Show { id } => {
+                let file: MediaFile = self.get(&format!("/api/v1/files/{}", id))?;
+                self.print(&file, print_file)
+            }
+            FileCommand::Upload { paths, conflict } => {
+                for path in paths {
+                    let files = self.upload(&path, &conflict)?;
+                    self.print(&files, |files| files.iter().for_each(print_file))?;
+                }
+                Ok(())
+            }
+            FileCommand::Delete {
+                id,
+                force,
+                cascade,
+                reassign,
+            } => {
+                let mut path = format!("/api/v1/files/{}?force={}&cascade={}", id, force, cascade);
+                if let Some(reassign) = reassign {
+                    path = format!("{}&reassign={}", path, reassign);
+                }
+                self.send::<()>("DELETE", &path, None)
+            }
+        }
+    }
+
+    fn schedules(&self, command: ScheduleCommand) -> Result<(), String> {
+        match command {
+            ScheduleCommand::List => {
+                let schedules: Vec<Schedule> = self.get("/api/v1/schedules")?;
+                self.print(&schedules, |schedules| {
+                    schedules.iter().for_each(print_schedule)
+                })
+            }
+            ScheduleCommand::Show { id } => {
+                let schedule: Schedule = self.get(&format!("/api/v1/schedules/{}", id))?;
+                self.print(&schedule, print_schedule)
+            }
+            ScheduleCommand::Add {
+                file,
+                options,
+                inactive,
+            } => {
+                let request = ScheduleRequest {
+                    file_id: file,
+                    schedule: definition(&options)?
+                        .ok_or("one of --cron, --at or --every is required")?,
+                    pre_roll: options.pre_roll,
+                    post_roll: options.post_roll,
+                    zone: options.zone,
+                    active: !inactive,
+                };
+                let schedule: Schedule = self.request("POST", "/api/v1/schedules", &request)?;
+                self.print(&schedule, print_schedule)
+            }
+            ScheduleCommand::Edit { id, file, options } => {
+                let path = format!("/api/v1/schedules/{}", id);
+                let current: Schedule = self.get(&path)?;
+                let request = ScheduleRequest {
+                    file_id: file.unwrap_or(current.file_id),
+                    schedule: match definition(&options)? {
+                        Some(definition) => definition,
+                        None => match current.spec {
+                            Some(spec) => ScheduleDefinition::Spec(spec),
+                            None => ScheduleDefinition::Cron(current.schedule),
+                        },
+                    },
+                    pre_roll: options.pre_roll.or(current.pre_roll),
+                    post_roll: options.post_roll.or(current.post_roll),
+                    zone: options.zone.or(current.zone),
+                    active: current.activity == Activity::Active,
+                };
+                let schedule: Schedule = self.request("PUT", &path, &request)?;
+                self.print(&schedule, print_schedule)
+            }
+            ScheduleCommand::Activate { id } => self.activate(id, true),
+            ScheduleCommand::Deactivate { id } => self.activate(id, false),
+            ScheduleCommand::Delete { id } => {
+                self.send::<()>("DELETE", &format!("/api/v1/schedules/{}", id), None)
+            }
+        }
+    }
+
+    fn activate(&self, id: u32, active: bool) -> Result<(), String> {
+        let update = UpdateSchedule {
+            active: Some(active),
+        };
+        let schedule: Schedule =
+            self.request("PATCH", &format!("/api/v1/schedules/{}", id), &update)?;
+        self.print(&schedule, print_schedule)
+    }
+
+    fn control(&self, action: &str, zone: Option<String>) -> Result<(), String> {
+        let mut path = format!("/api/v1/playback/{}", action);
+        if let Some(zone) = zone {
+            path = format!("{}?zone={}", path, percent_encode(&zone));
+        }
+        self.send::<()>("POST", &path, None)
+    }
+
+    /// Prints the events logged so far, then polls for new ones if `follow` is set.
+    fn events(&self, level: &str, follow: bool) -> Result<(), String> {
+        let mut since: Option<String> = None;
+        loop {
+            let mut path = format!("/api/v1/events?level={}", percent_encode(level));
+            if let Some(since) = &since {
+                path = format!("{}&since={}", path, percent_encode(since));
+            }
+            let entries: Vec<LogEntry> = self.get(&path)?;
+            for entry in &entries {
+                if self.json {
+                    println!("{}", serde_json::to_string(entry).unwrap());
+                } else {
+                    print_event(entry);
+                }
+            }
+            if !follow {
+                return Ok(());
+            }
+            if let Some(last) = entries.last() {
+                since = Some(last.time.clone());
+            }
+            std::thread::sleep(POLL_INTERVAL);
+        }
+    }
+
+    fn upload(&self, path: &Path, conflict: &str) -> Result<Vec<MediaFile>, String> {
+        let name = path
+            .file_name()
+            .ok_or_else(|| format!("{} is not a file", path.display()))?
+            .to_string_lossy();
+        let content =
+            std::fs::read(path).map_err(|e| format!("could not read {}: {}", path.display(), e))?;
+        let boundary = format!("rustyplayer-{}", uuid::Uuid::new_v4().simple());
+        let mut body = format!(
+            "--{}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"{}\"\r\nContent-Type: application/octet-stream\r\n\r\n",
+            boundary,
+            name.replace('"', "%22")
+        )
+        .into_bytes();
+        body.extend_from_slice(&content);
+        body.extend_from_slice(format!("\r\n--{}--\r\n", boundary).as_bytes());
+        let response = self
+            .agent
+            .post(&format!("{}/api/v1/files?conflict={}", self.url, conflict))
+            .set(
+                "content-type",
+                &format!("multipart/form-data; boundary={}", boundary),
+            )
+            .send_bytes(&body)
+            .map_err(describe)?;
+        parse(response)
+    }
+
+    fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T, String> {
+        parse(self.call::<()>("GET", path, None)?)
+    }
+
+    fn request<T: DeserializeOwned>(
+        &self,
+        method: &str,
+        path: &str,
+        body: &impl Serialize,
+    ) -> Result<T, String> {
+        parse(self.call(method, path, Some(body))?)
+    }
+
+    /// Sends a request that answers with no content.
+    fn send<T: Serialize>(&self, method: &str, path: &str, body: Option<&T>) -> Result<(), String> {
+        self.call(method, path, body).map(|_| ())
+    }
+
+    fn call<T: Serialize>(
+        &self,
+        method: &str,
+        path: &str,
+        body: Option<&T>,
+    ) -> Result<ureq::Response, String> {
+        let request = self.agent.request(method, &format!("{}{}", self.url, path));
+        match body {
+            Some(body) => request
+                .set("content-type", "application/json")
+                .send_string(&serde_json::to_string(body).unwrap()),
+            None => request.call(),
+        }
+        .map_err(describe)
+    }
+
+    fn print<T: Serialize>(&self, value: &T, human: impl Fn(&T)) -> Result<(), String> {
+        if self.json {
+            println!("{}", serde_json::to_string_pretty(value).unwrap());
+        } else {
+            human(value);
+        }
+        Ok(())
+    }
+}
+
+fn parse<T: DeserializeOwned>(response: ureq::Response) -> Result<T, String> {
+    let body = response
+        .into_string()
+        .map_err(|e| format!("could not read the response: {}", e))?;
+    serde_json::from_str(&body).map_err(|e| format!("invalid response: {}", e))
+}
+
+/// Turns an error response into the message the API sent with it.
+fn describe(error: ureq::Error) -> String {
+    match error {
+        ureq::Error::Status(status, response) => {
+            let body = response.into_string().unwrap_or_default();
+            match serde_json::from_str::<ErrorResponse>(&body) {
+                Ok(error) => format!("{} ({})", error.message, status),
+                Err(_) if !body.is_empty() => format!("{} ({})", body.trim(), status),
+                Err(_) => format!("request failed with status {}", status),
+            }
+        }
+        ureq::Error::Transport(e) => e.to_string(),
+    }
+}
+
+fn definition(options: &ScheduleOptions) -> Result<Option<ScheduleDefinition>, String> {
+    if let Some(cron) = &options.cron {
+        return Ok(Some(ScheduleDefinition::Cron(cron.clone())));
+    }
+    let every = match (options.every, &options.from, &options.to) {
+        (Some(minutes), Some(from), Some(to)) => Some(Interval {
+            minutes,
+            from: from.clone(),
+            to: to.clone(),
+        }),
+        _ => None,
+    };
+    if options.at.is_empty() && every.is_none() {
+        if !options.days.is_empty() {
+            return Err("--days needs --at or --every".to_string());
+        }
+        return Ok(None);
+    }
+    Ok(Some(ScheduleDefinition::Spec(ScheduleSpec {
+        days: options.days.clone(),
+        times: options.at.clone(),
+        every,
+    })))
+}
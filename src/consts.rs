@@ -1,17 +1,35 @@
-pub const MEDIA_PATH: &str = if cfg!(debug_assertions) {
+/// Tests get their own store under `target`, so they never touch the real one.
+pub const MEDIA_PATH: &str = if cfg!(test) {
+    "target/test-store/media"
+} else if cfg!(debug_assertions) {
     "media"
 } else {
     "/media"
 };
 
-pub const RESOURCE_PATH: &str = if cfg!(debug_assertions) {
+pub const RESOURCE_PATH: &str = if cfg!(test) {
+    "target/test-store/resource"
+} else if cfg!(debug_assertions) {
     "resource"
 } else {
     "/resource"
 };
 
-pub const WEB_PATH: &str = if cfg!(debug_assertions) {
-    "static"
-} else {
-    "/static"
-};
+/// Default upload limit, overridable with the `MAX_UPLOAD_SIZE` environment variable.
+pub const MAX_UPLOAD_SIZE: u64 = 64 * 1024 * 1024;
+
+/// Default normalization target in LUFS, overridable with `TARGET_LOUDNESS`.
+pub const TARGET_LOUDNESS: f64 = -18.0;
+
+/// Default offline speech engine, overridable with `TTS_COMMAND`.
+pub const TTS_COMMAND: &str = "espeak-ng --stdin -w {output}";
+
+/// Default log filter, overridable with `LOG_LEVEL`, e.g. `warn,rustyplayer=debug`.
+/// The decoders log every probed file at info.
+pub const LOG_LEVEL: &str = "info,symphonia=warn";
+
+/// Default log output, `text` or `json`, overridable with `LOG_FORMAT`.
+pub const LOG_FORMAT: &str = "text";
+
+/// Default zone setup, overridable with `ZONES`, e.g. `office=default;hallway=USB Audio`.
+pub const ZONES: &str = "default=default";
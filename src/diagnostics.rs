@@ -0,0 +1,29 @@
+use std::path::Path;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+use crate::models::DiskSpace;
+
+static STARTED: OnceLock<Instant> = OnceLock::new();
+
+/// Marks the start of the process, call once from `main`.
+pub fn init() {
+    STARTED.get_or_init(Instant::now);
+}
+
+pub fn uptime() -> Duration {
+    STARTED.get().map(|s| s.elapsed()).unwrap_or_default()
+}
+
+pub fn disk_space(path: &Path) -> Option<DiskSpace> {
+    let space = || {
+        Ok::<_, std::io::Error>(DiskSpace {
+            available: fs2::available_space(path)?,
+            total: fs2::total_space(path)?,
+        })
+    };
+    space()
+        .map_err(|e| warn!("error reading disk space of {}: {}", path.display(), e))
+        .ok()
+}
@@ -0,0 +1,68 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::generator::Generator;
+use crate::schedule_spec::ScheduleDefinition;
+
+/// Body of every error returned under `/api/v1`.
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+pub struct ErrorResponse {
+    pub status: u16,
+    pub message: String,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+pub struct CreateGenerator {
+    pub name: String,
+    pub generator: Generator,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+pub struct CreateStream {
+    pub name: String,
+    pub url: String,
+}
+
+/// Fields left out are not changed.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, ToSchema)]
+pub struct UpdateFile {
+    pub normalize: Option<bool>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+pub struct TrimRequest {
+    pub start: f64,
+    pub end: Option<f64>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+pub struct PlayRequest {
+    pub file_id: u32,
+    #[serde(default)]
+    pub pre_roll: Option<u32>,
+    #[serde(default)]
+    pub post_roll: Option<u32>,
+    #[serde(default)]
+    pub zone: Option<String>,
+}
+
+/// Full schedule as created with POST or replaced with PUT.
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+pub struct ScheduleRequest {
+    pub file_id: u32,
+    pub schedule: ScheduleDefinition,
+    #[serde(default)]
+    pub pre_roll: Option<u32>,
+    #[serde(default)]
+    pub post_roll: Option<u32>,
+    #[serde(default)]
+    pub zone: Option<String>,
+    #[serde(default)]
+    pub active: bool,
+}
+
+/// Fields left out are not changed.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, ToSchema)]
+pub struct UpdateSchedule {
+    pub active: Option<bool>,
+}
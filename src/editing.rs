@@ -0,0 +1,180 @@
+use hound::{SampleFormat, WavSpec, WavWriter};
+use rodio::{Decoder, Source};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+/// Samples quieter than this (in dBFS) count as silence.
+const SILENCE_THRESHOLD_DB: f64 = -50.0;
+
+/// Edit applied while rendering a new version of a media file, which is
+/// always written as 16 bit PCM WAV at the source sample rate.
+#[derive(Clone, Debug, Default)]
+pub struct Edit {
+    /// Start offset in seconds.
+    pub start: Option<f64>,
+    /// End offset in seconds.
+    pub end: Option<f64>,
+    pub strip_silence: bool,
+}
+
+impl Edit {
+    pub fn label(&self) -> &'static str {
+        if self.strip_silence {
+            "without silence"
+        } else if self.start.is_some() || self.end.is_some() {
+            "trimmed"
+        } else {
+            "converted"
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.start.is_some_and(|s| !s.is_finite() || s < 0.0)
+            || self.end.is_some_and(|e| !e.is_finite() || e < 0.0)
+        {
+            return Err("offsets must be positive".to_string());
+        }
+        if let (Some(start), Some(end)) = (self.start, self.end) {
+            if start >= end {
+                return Err(format!("start {} is not before end {}", start, end));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Streams the decoded source into `target`, so whole recordings never have to fit in memory.
+/// Stripping silence takes a first pass to find where the audio ends.
+pub fn render(source: &Path, target: &Path, edit: &Edit) -> Result<(), String> {
+    let mut decoder = open(source)?;
+    let channels = decoder.channels();
+    let sample_rate = decoder.sample_rate();
+    let mut first_frame = edit.start.map_or(0, |s| (s * sample_rate as f64) as u64);
+    let mut last_frame = edit.end.map(|e| (e * sample_rate as f64) as u64);
+    if edit.strip_silence {
+        let (first, last) = audible_span(source, first_frame, last_frame)?
+            .ok_or("edit leaves no audio".to_string())?;
+        first_frame = first;
+        last_frame = Some(last + 1);
+    }
+
+    let spec = WavSpec {
+        channels,
+        sample_rate,
+        bits_per_sample: 16,
+        sample_format: SampleFormat::Int,
+    };
+    let mut writer = WavWriter::create(target, spec).map_err(|e| e.to_string())?;
+
+    let mut frame = Vec::with_capacity(channels as usize);
+    let mut written = 0;
+    let mut position = 0;
+    while last_frame.is_none_or(|last| position < last) {
+        frame.clear();
+        frame.extend(decoder.by_ref().take(channels as usize));
+        if frame.len() < channels as usize {
+            break;
+        }
+        position += 1;
+        if position <= first_frame {
+            continue;
+        }
+        for sample in frame.iter() {
+            writer.write_sample(*sample).map_err(|e| e.to_string())?;
+        }
+        written += 1;
+    }
+    writer.finalize().map_err(|e| e.to_string())?;
+
+    if written == 0 {
+        return Err("edit leaves no audio".to_string());
+    }
+    Ok(())
+}
+
+fn open(source: &Path) -> Result<Decoder<BufReader<File>>, String> {
+    let file = File::open(source).map_err(|e| e.to_string())?;
+    Decoder::new(BufReader::new(file)).map_err(|e| e.to_string())
+}
+
+/// First and last audible frame from `first_frame` up to `last_frame`, if any.
+fn audible_span(
+    source: &Path,
+    first_frame: u64,
+    last_frame: Option<u64>,
+) -> Result<Option<(u64, u64)>, String> {
+    let mut decoder = open(source)?;
+    let channels = decoder.channels() as usize;
+    let threshold = (10f64.powf(SILENCE_THRESHOLD_DB / 20.0) * i16::MAX as f64) as u16;
+    let mut frame = Vec::with_capacity(channels);
+    let mut span = None;
+    let mut position = 0;
+    while last_frame.is_none_or(|last| position < last) {
+        frame.clear();
+        frame.extend(decoder.by_ref().take(channels));
+        if frame.len() < channels {
+            break;
+        }
+        if position >= first_frame && frame.iter().any(|s| s.unsigned_abs() > threshold) {
+            span = Some((span.map_or(position, |(first, _)| first), position));
+        }
+        position += 1;
+    }
+    Ok(span)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_wav(path: &Path, samples: &[i16]) {
+        let spec = WavSpec {
+            channels: 1,
+            sample_rate: 8000,
+            bits_per_sample: 16,
+            sample_format: SampleFormat::Int,
+        };
+        let mut writer = WavWriter::create(path, spec).unwrap();
+        for sample in samples {
+            writer.write_sample(*sample).unwrap();
+        }
+        writer.finalize().unwrap();
+    }
+
+    fn read_wav(path: &Path) -> Vec<i16> {
+        hound::WavReader::open(path)
+            .unwrap()
+            .samples::<i16>()
+            .map(|s| s.unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn strips_leading_and_trailing_silence_only() {
+        let dir = std::env::temp_dir().join(format!("editing-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let source = dir.join("source.wav");
+        let target = dir.join("target.wav");
+        let mut samples = vec![0; 800];
+        samples.extend([8000, -8000]);
+        samples.extend([3; 400]);
+        samples.extend([8000, -8000]);
+        samples.extend(vec![0; 800]);
+        write_wav(&source, &samples);
+
+        let edit = Edit {
+            strip_silence: true,
+            ..Edit::default()
+        };
+        render(&source, &target, &edit).unwrap();
+        assert_eq!(read_wav(&target), samples[800..1204]);
+
+        write_wav(&source, &[0; 800]);
+        assert_eq!(
+            render(&source, &target, &edit),
+            Err("edit leaves no audio".to_string())
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,149 @@
+use rodio::{Decoder, Source};
+use std::fs::File;
+use std::io::{BufReader, Read, Seek};
+use std::path::Path;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AudioFormat {
+    Mp3,
+    Ogg,
+    Wav,
+    Flac,
+    M4a,
+    Aac,
+}
+
+impl AudioFormat {
+    /// Detects the container from the leading bytes of the file, ignoring
+    /// whatever content type or file name the client claims.
+    pub fn sniff(data: &[u8]) -> Option<AudioFormat> {
+        match data {
+            [b'I', b'D', b'3', ..] => Some(AudioFormat::Mp3),
+            [b'O', b'g', b'g', b'S', ..] => Some(AudioFormat::Ogg),
+            [b'f', b'L', b'a', b'C', ..] => Some(AudioFormat::Flac),
+            [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'A', b'V', b'E', ..] => {
+                Some(AudioFormat::Wav)
+            }
+            [_, _, _, _, b'f', b't', b'y', b'p', ..] => Some(AudioFormat::M4a),
+            // ADTS and MPEG audio share the 12 bit sync word, layer bits 00 mean AAC
+            [0xFF, b, ..] if b & 0xF6 == 0xF0 => Some(AudioFormat::Aac),
+            [0xFF, b, ..] if b & 0xE0 == 0xE0 => Some(AudioFormat::Mp3),
+            _ => None,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            AudioFormat::Mp3 => "mp3",
+            AudioFormat::Ogg => "ogg",
+            AudioFormat::Wav => "wav",
+            AudioFormat::Flac => "flac",
+            AudioFormat::M4a => "m4a",
+            AudioFormat::Aac => "aac",
+        }
+    }
+}
+
+/// Sniffs the format and makes sure rodio can actually decode some audio out of it.
+pub fn probe(path: &Path) -> Result<AudioFormat, String> {
+    let mut file = File::open(path).map_err(|e| e.to_string())?;
+    let mut header = vec![];
+    (&mut file)
+        .take(12)
+        .read_to_end(&mut header)
+        .map_err(|e| e.to_string())?;
+    let format = AudioFormat::sniff(&header).ok_or("unrecognized audio format")?;
+    file.rewind().map_err(|e| e.to_string())?;
+    let mut decoder = Decoder::new(BufReader::new(file))
+        .map_err(|e| format!("failed to decode {:?}: {}", format, e))?;
+    if decoder.channels() == 0 || decoder.sample_rate() == 0 || decoder.next().is_none() {
+        return Err(format!("{:?} file contains no audio", format));
+    }
+    Ok(format)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn temp_file(name: &str, content: &[u8]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("format-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        std::fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn frame_sync_tells_adts_from_mpeg() {
+        // ADTS, MPEG-4 and MPEG-2 with and without CRC
+        for header in [[0xFF, 0xF1], [0xFF, 0xF0], [0xFF, 0xF9], [0xFF, 0xF8]] {
+            assert_eq!(AudioFormat::sniff(&header), Some(AudioFormat::Aac));
+        }
+        // MPEG-1 and MPEG-2 layer III, MPEG-1 layer II
+        for header in [[0xFF, 0xFB], [0xFF, 0xF3], [0xFF, 0xFD], [0xFF, 0xE3]] {
+            assert_eq!(AudioFormat::sniff(&header), Some(AudioFormat::Mp3));
+        }
+        assert_eq!(AudioFormat::sniff(&[0xFF, 0xD8, 0xFF, 0xE0]), None);
+    }
+
+    #[test]
+    fn magic_bytes() {
+        assert_eq!(AudioFormat::sniff(b"ID3\x04\0\0"), Some(AudioFormat::Mp3));
+        assert_eq!(AudioFormat::sniff(b"OggS\0\x02"), Some(AudioFormat::Ogg));
+        assert_eq!(
+            AudioFormat::sniff(b"fLaC\0\0\0\x22"),
+            Some(AudioFormat::Flac)
+        );
+        assert_eq!(
+            AudioFormat::sniff(b"RIFF\x24\x08\0\0WAVEfmt "),
+            Some(AudioFormat::Wav)
+        );
+        assert_eq!(
+            AudioFormat::sniff(b"\0\0\0\x20ftypM4A "),
+            Some(AudioFormat::M4a)
+        );
+        // RIFF, but not a wave file
+        assert_eq!(AudioFormat::sniff(b"RIFF\x24\x08\0\0AVI LIST"), None);
+        assert_eq!(AudioFormat::sniff(b"%PDF-1.7"), None);
+        assert_eq!(AudioFormat::sniff(b""), None);
+    }
+
+    #[test]
+    fn probes_id3_tagged_mp3() {
+        let path = Path::new("media/tripple_kill.mp3");
+        assert!(std::fs::read(path).unwrap().starts_with(b"ID3"));
+        assert_eq!(probe(path), Ok(AudioFormat::Mp3));
+    }
+
+    #[test]
+    fn probes_wav() {
+        let path = temp_file("tone.wav", b"");
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: 8000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+        for sample in 0..800 {
+            writer.write_sample((sample % 100) as i16 * 100).unwrap();
+        }
+        writer.finalize().unwrap();
+        assert_eq!(probe(&path), Ok(AudioFormat::Wav));
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn rejects_non_audio() {
+        let text = temp_file("notes.mp3", b"just some text, named like audio");
+        assert_eq!(probe(&text), Err("unrecognized audio format".to_string()));
+        std::fs::remove_dir_all(text.parent().unwrap()).unwrap();
+
+        // the right magic bytes but nothing decodable behind them
+        let fake = temp_file("fake.flac", b"fLaC and then garbage instead of a stream");
+        assert!(probe(&fake).is_err());
+        std::fs::remove_dir_all(fake.parent().unwrap()).unwrap();
+    }
+}
@@ -0,0 +1,176 @@
+use rodio::Source;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
+use std::time::Duration;
+use utoipa::ToSchema;
+
+pub const SAMPLE_RATE: u32 = 44100;
+const AMPLITUDE: f32 = 0.5;
+/// Fade at the edges of every tone, avoids clicks.
+const RAMP: f32 = 0.005;
+const MAX_DURATION: f32 = 600.0;
+
+/// Synthesized signal, played without any backing file.
+/// Durations are in seconds, frequencies in Hz.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, ToSchema)]
+pub enum Generator {
+    Sine {
+        frequency: f32,
+        duration: f32,
+    },
+    /// Tones of the given lengths separated by `gap`, e.g. `[0.2, 0.2, 0.2, 1.0]`.
+    Beeps {
+        frequency: f32,
+        beeps: Vec<f32>,
+        gap: f32,
+    },
+    Sweep {
+        from: f32,
+        to: f32,
+        duration: f32,
+    },
+    Noise {
+        duration: f32,
+    },
+}
+
+impl Generator {
+    pub fn validate(&self) -> Result<(), String> {
+        let frequencies = match self {
+            Generator::Sine { frequency, .. } | Generator::Beeps { frequency, .. } => {
+                vec![*frequency]
+            }
+            Generator::Sweep { from, to, .. } => vec![*from, *to],
+            Generator::Noise { .. } => vec![],
+        };
+        if let Some(frequency) = frequencies.iter().find(|f| !(20.0..=20000.0).contains(*f)) {
+            return Err(format!("frequency {} Hz is out of range", frequency));
+        }
+        if let Generator::Beeps { beeps, gap, .. } = self {
+            if beeps.is_empty() {
+                return Err("beep pattern is empty".to_string());
+            }
+            if beeps.iter().any(|b| !b.is_finite() || *b <= 0.0) || !gap.is_finite() || *gap < 0.0 {
+                return Err("beep lengths must be positive".to_string());
+            }
+        }
+        let duration = self.duration();
+        if !duration.is_finite() || duration <= 0.0 || duration > MAX_DURATION {
+            return Err(format!("duration must be between 0 and {} s", MAX_DURATION));
+        }
+        Ok(())
+    }
+
+    pub fn duration(&self) -> f32 {
+        match self {
+            Generator::Sine { duration, .. }
+            | Generator::Sweep { duration, .. }
+            | Generator::Noise { duration } => *duration,
+            Generator::Beeps { beeps, gap, .. } => {
+                beeps.iter().sum::<f32>() + gap * (beeps.len() as f32 - 1.0)
+            }
+        }
+    }
+
+    pub fn source(&self) -> Signal {
+        let length = (self.duration() * SAMPLE_RATE as f32) as u64;
+        let time = |position: u64| position as f32 / SAMPLE_RATE as f32;
+        let sample: Box<dyn FnMut(u64) -> f32 + Send> = match self.clone() {
+            Generator::Sine {
+                frequency,
+                duration,
+            } => Box::new(move |position| {
+                let t = time(position);
+                envelope(t, duration) * (2.0 * PI * frequency * t).sin()
+            }),
+            Generator::Beeps {
+                frequency,
+                beeps,
+                gap,
+            } => {
+                let mut tones = vec![];
+                let mut start = 0.0;
+                for beep in beeps {
+                    tones.push((start, beep));
+                    start += beep + gap;
+                }
+                Box::new(move |position| {
+                    let t = time(position);
+                    match tones
+                        .iter()
+                        .find(|(start, length)| t >= *start && t < start + length)
+                    {
+                        Some((start, length)) => {
+                            envelope(t - start, *length) * (2.0 * PI * frequency * t).sin()
+                        }
+                        None => 0.0,
+                    }
+                })
+            }
+            Generator::Sweep { from, to, duration } => Box::new(move |position| {
+                let t = time(position);
+                let phase = 2.0 * PI * (from * t + (to - from) * t * t / (2.0 * duration));
+                envelope(t, duration) * phase.sin()
+            }),
+            Generator::Noise { duration } => {
+                let mut seed: u32 = 0x9E37_79B9;
+                Box::new(move |position| {
+                    // xorshift, plenty random for noise
+                    seed ^= seed << 13;
+                    seed ^= seed >> 17;
+                    seed ^= seed << 5;
+                    let value = seed as f32 / u32::MAX as f32 * 2.0 - 1.0;
+                    envelope(time(position), duration) * value
+                })
+            }
+        };
+        Signal {
+            position: 0,
+            length,
+            sample,
+        }
+    }
+}
+
+fn envelope(t: f32, length: f32) -> f32 {
+    AMPLITUDE * (t / RAMP).min((length - t) / RAMP).clamp(0.0, 1.0)
+}
+
+pub struct Signal {
+    position: u64,
+    length: u64,
+    sample: Box<dyn FnMut(u64) -> f32 + Send>,
+}
+
+impl Iterator for Signal {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.position >= self.length {
+            return None;
+        }
+        let sample = (self.sample)(self.position);
+        self.position += 1;
+        Some(sample)
+    }
+}
+
+impl Source for Signal {
+    fn current_frame_len(&self) -> Option<usize> {
+        Some((self.length - self.position) as usize)
+    }
+
+    fn channels(&self) -> u16 {
+        1
+    }
+
+    fn sample_rate(&self) -> u32 {
+        SAMPLE_RATE
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        Some(Duration::from_secs_f32(
+            self.length as f32 / SAMPLE_RATE as f32,
+        ))
+    }
+}
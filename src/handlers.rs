@@ -1,203 +1,1430 @@
-use bytes::BufMut;
+use bytes::Buf;
 use futures::TryStreamExt;
 use hyper::Uri;
 use std::convert::Infallible;
+use std::path::{Path, PathBuf};
+use tracing::{error, info, warn};
+use utoipa::OpenApi;
+use uuid::Uuid;
 use warp::multipart::{FormData, Part};
 use warp::{self, http::StatusCode, reject::Reject, Rejection};
 
-use crate::models::{Activity, Schedule, Status};
-use crate::utils::remove_file;
-use crate::utils::write_file;
-use crate::PlayerMutex;
-use crate::{SchedulerMutex, StateMutex};
+use crate::consts::{MEDIA_PATH, RESOURCE_PATH};
+use crate::diagnostics::{disk_space, uptime};
+use crate::dto::{
+    CreateGenerator, CreateStream, ErrorResponse, PlayRequest, ScheduleRequest, TrimRequest,
+    UpdateFile, UpdateSchedule,
+};
+use crate::editing::{render, Edit};
+use crate::format::probe;
+use crate::generator::Generator;
+use crate::library;
+use crate::logging::{last_error, recent};
+use crate::loudness::analyze_loudness;
+use crate::metadata::{extract_metadata, MediaMetadata};
+use crate::metrics::{directory_size, METRICS};
+use crate::models::{
+    ActiveUpload, Activity, Announcement, Conflict, DeleteOptions, Diagnostics, EditedSchedule,
+    LogEntry, LogQuery, MediaFile, NewSchedule, PlayOptions, Readiness, Reconciliation, Schedule,
+    State, Status, StatusReport, UploadOptions, UploadProgress, ZoneOptions, ZoneStatus,
+};
+use crate::openapi::ApiDoc;
+use crate::player::Player;
+use crate::schedule_spec::{ScheduleDefinition, ScheduleSpec};
+use crate::stream::validate_url;
+use crate::tts::synthesize;
+use crate::upload::PartialUpload;
+use crate::utils::{hash_file, percent_encode, remove_file};
+use crate::utils::{max_upload_size, sanitize_file_name, tts_command};
+use crate::web;
+use crate::zones::{output_devices, Zones};
+use crate::{SchedulerMutex, StateMutex, ZonesMutex};
 
 #[derive(Debug)]
-struct InvalidFile;
+pub struct InvalidFile;
 impl Reject for InvalidFile {}
 
-pub async fn get_status(
-    state: StateMutex,
-    player: PlayerMutex,
-) -> Result<impl warp::Reply, Infallible> {
-    let mut state = state.lock().await;
-    if state.status == Status::Running {
-        let player = player.lock().await;
-        if player.done() {
-            state.status = Status::Idle;
+#[derive(Debug)]
+pub struct InvalidSchedule(pub String);
+impl Reject for InvalidSchedule {}
+
+#[derive(Debug)]
+pub struct DuplicateFile(pub u32);
+impl Reject for DuplicateFile {}
+
+#[derive(Debug)]
+pub struct FileTooLarge(pub u64);
+impl Reject for FileTooLarge {}
+
+#[derive(Debug)]
+pub struct InvalidEdit(pub String);
+impl Reject for InvalidEdit {}
+
+#[derive(Debug)]
+pub struct InvalidAnnouncement(pub String);
+impl Reject for InvalidAnnouncement {}
+
+#[derive(Debug)]
+pub struct SpeechFailed(pub String);
+impl Reject for SpeechFailed {}
+
+#[derive(Debug)]
+pub struct InvalidGenerator(pub String);
+impl Reject for InvalidGenerator {}
+
+#[derive(Debug)]
+pub struct InvalidStream(pub String);
+impl Reject for InvalidStream {}
+
+#[derive(Debug)]
+pub struct InvalidLogQuery(pub String);
+impl Reject for InvalidLogQuery {}
+
+#[derive(Debug)]
+pub struct FileNotFound(pub u32);
+impl Reject for FileNotFound {}
+
+#[derive(Debug)]
+pub struct ScheduleNotFound(pub u32);
+impl Reject for ScheduleNotFound {}
+
+#[derive(Debug)]
+pub struct FileInUse(pub u32, pub Vec<u32>);
+impl Reject for FileInUse {}
+
+#[derive(Debug)]
+pub struct InvalidDeletion(pub String);
+impl Reject for InvalidDeletion {}
+
+#[derive(Debug)]
+pub struct DeletionFailed(pub String);
+impl Reject for DeletionFailed {}
+
+#[derive(Debug)]
+pub struct MediaMissing(pub u32);
+impl Reject for MediaMissing {}
+
+#[derive(Debug)]
+pub struct ScanFailed(pub String);
+impl Reject for ScanFailed {}
+
+#[derive(Debug)]
+pub struct UnknownZone(pub String);
+impl Reject for UnknownZone {}
+
+#[derive(Debug)]
+pub struct ZoneUnavailable(pub String);
+impl Reject for ZoneUnavailable {}
+
+fn check_zone(zones: &Zones, zone: Option<&str>) -> Result<(), Rejection> {
+    match zone {
+        Some(zone) if zones.get(Some(zone)).is_none() => {
+            Err(warp::reject::custom(UnknownZone(zone.to_string())))
         }
+        _ => Ok(()),
+    }
+}
+
+fn select_zones(zones: &Zones, options: ZoneOptions) -> Result<Vec<&Player>, Rejection> {
+    zones
+        .select(options.zone.as_deref())
+        .ok_or_else(|| warp::reject::custom(UnknownZone(options.zone.unwrap_or_default())))
+}
+
+fn created<T: serde::Serialize>(value: &T) -> impl warp::Reply {
+    warp::reply::with_status(warp::reply::json(value), StatusCode::CREATED)
+}
+
+/// Disk file of an entry that goes away with it, unless it's gone already or another entry uses it.
+fn owned_path(state: &State, file: &MediaFile) -> Option<String> {
+    (file.kind.is_file() && !file.missing && !state.is_path_shared(file.id, &file.path))
+        .then(|| file.path.clone())
+}
+
+async fn delete_from_disk(path: &str) -> Result<(), Rejection> {
+    remove_file(path).await.map_err(|e| {
+        error!("error deleting file {}: {}", path, e);
+        warp::reject::custom(DeletionFailed(format!("could not delete {}: {}", path, e)))
+    })
+}
+
+/// Where an edit or synthesis is rendered, under the hidden `.upload` directory so the
+/// library watcher can't adopt it half-written, and where it is moved once stored.
+async fn render_paths() -> Result<(PathBuf, PathBuf), String> {
+    let name = Path::new(&Uuid::new_v4().to_string()).with_extension("wav");
+    let scratch = Path::new(MEDIA_PATH).join(".upload");
+    tokio::fs::create_dir_all(&scratch)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok((scratch.join(&name), Path::new(MEDIA_PATH).join(name)))
+}
+
+/// Cleans up the output of a failed edit or synthesis.
+async fn discard(target: &Path) {
+    if let Err(e) = remove_file(&target.to_string_lossy()).await {
+        warn!("error deleting {}: {}", target.display(), e);
     }
-    Ok(warp::reply::json(&state.status))
 }
 
+fn find_file(state: &State, id: u32) -> Result<&MediaFile, Rejection> {
+    state
+        .get_media(id)
+        .ok_or_else(|| warp::reject::custom(FileNotFound(id)))
+}
+
+fn find_schedule(state: &State, id: u32) -> Result<&Schedule, Rejection> {
+    state
+        .get_schedule(id)
+        .ok_or_else(|| warp::reject::custom(ScheduleNotFound(id)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/status",
+    tag = "playback",
+    summary = "Playback status of every zone",
+    responses(
+        (status = 200, description = "Status of the default zone and all zones", body = StatusReport),
+    )
+)]
+pub async fn get_status(zones: ZonesMutex) -> Result<impl warp::Reply, Infallible> {
+    let zones = zones.lock().await.status();
+    let default = zones[0].clone();
+    Ok(warp::reply::json(&StatusReport {
+        status: default.status,
+        stream: default.stream,
+        zones,
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/zones",
+    tag = "playback",
+    summary = "Configured zones",
+    responses(
+        (status = 200, description = "Zones", body = [ZoneStatus]),
+    )
+)]
+pub async fn get_zones(zones: ZonesMutex) -> Result<impl warp::Reply, Infallible> {
+    let zones = zones.lock().await;
+    Ok(warp::reply::json(&zones.status()))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/devices",
+    tag = "playback",
+    summary = "Audio output devices",
+    responses(
+        (status = 200, description = "Device names", body = [String]),
+    )
+)]
+pub async fn get_devices() -> Result<impl warp::Reply, Infallible> {
+    let devices = tokio::task::spawn_blocking(output_devices)
+        .await
+        .unwrap_or_default();
+    Ok(warp::reply::json(&devices))
+}
+
+#[utoipa::path(
+    get,
+    path = "/health",
+    tag = "monitoring",
+    summary = "Liveness check",
+    responses(
+        (status = 200, description = "The service is running", body = String),
+    )
+)]
+pub async fn health() -> Result<impl warp::Reply, Infallible> {
+    Ok(warp::reply::json(&"OK"))
+}
+
+#[utoipa::path(
+    get,
+    path = "/ready",
+    tag = "monitoring",
+    summary = "Readiness check",
+    responses(
+        (status = 200, description = "Storage, audio and scheduler are ready", body = Readiness),
+        (status = 503, description = "Not ready", body = Readiness),
+    )
+)]
+pub async fn ready(
+    zones: ZonesMutex,
+    scheduler: SchedulerMutex,
+) -> Result<impl warp::Reply, Infallible> {
+    let storage = Path::new(MEDIA_PATH).is_dir() && Path::new(RESOURCE_PATH).is_dir();
+    let audio = zones.lock().await.connected();
+    let scheduler = scheduler.lock().await.is_running();
+    let ready = storage && audio && scheduler;
+    let code = if ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    Ok(warp::reply::with_status(
+        warp::reply::json(&Readiness {
+            ready,
+            storage,
+            audio,
+            scheduler,
+        }),
+        code,
+    ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/diagnostics",
+    tag = "monitoring",
+    summary = "Version, uptime, disk space and zones",
+    responses(
+        (status = 200, description = "Diagnostics", body = Diagnostics),
+    )
+)]
+pub async fn get_diagnostics(
+    zones: ZonesMutex,
+    scheduler: SchedulerMutex,
+) -> Result<impl warp::Reply, Infallible> {
+    let disk = tokio::task::spawn_blocking(|| disk_space(Path::new(MEDIA_PATH)))
+        .await
+        .unwrap_or_default();
+    let zones = zones.lock().await.status();
+    let scheduler = scheduler.lock().await;
+    Ok(warp::reply::json(&Diagnostics {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        uptime: uptime().as_secs(),
+        disk,
+        active_schedules: scheduler.active_schedules(),
+        active_jobs: scheduler.active_jobs(),
+        zones,
+        last_error: last_error(),
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    tag = "monitoring",
+    summary = "Prometheus metrics",
+    responses(
+        (status = 200, description = "Metrics in the Prometheus text format", body = String, content_type = "text/plain"),
+    )
+)]
+pub async fn get_metrics(
+    zones: ZonesMutex,
+    scheduler: SchedulerMutex,
+) -> Result<impl warp::Reply, Infallible> {
+    let storage = tokio::task::spawn_blocking(|| directory_size(Path::new(MEDIA_PATH)))
+        .await
+        .unwrap_or_default();
+    METRICS.storage_bytes.set(storage as i64);
+    let active_schedules = scheduler.lock().await.active_schedules();
+    METRICS.active_schedules.set(active_schedules as i64);
+    for player in zones.lock().await.players() {
+        METRICS
+            .queue_length
+            .with_label_values(&[&player.zone().name])
+            .set(player.queue_len() as i64);
+    }
+    Ok(warp::reply::with_header(
+        METRICS.render(),
+        "content-type",
+        "text/plain; version=0.0.4",
+    ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/logs",
+    tag = "monitoring",
+    summary = "Recent log entries",
+    params(LogQuery),
+    responses(
+        (status = 200, description = "Buffered log entries, oldest first", body = [LogEntry]),
+        (status = 400, description = "Invalid since or level", body = String),
+    )
+)]
+pub async fn get_logs(query: LogQuery) -> Result<impl warp::Reply, Rejection> {
+    Ok(warp::reply::json(&log_entries(query)?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/events",
+    tag = "monitoring",
+    summary = "Recent log entries",
+    params(LogQuery),
+    responses(
+        (status = 200, description = "Buffered log entries, oldest first", body = [LogEntry]),
+        (status = 400, description = "Invalid since or level", body = ErrorResponse),
+    )
+)]
+pub async fn get_events(query: LogQuery) -> Result<impl warp::Reply, Rejection> {
+    Ok(warp::reply::json(&log_entries(query)?))
+}
+
+fn log_entries(query: LogQuery) -> Result<Vec<LogEntry>, Rejection> {
+    let since = query
+        .since
+        .map(|since| {
+            chrono::DateTime::parse_from_rfc3339(&since)
+                .map(|since| since.with_timezone(&chrono::Utc))
+                .map_err(|e| format!("invalid since {}: {}", since, e))
+        })
+        .transpose()
+        .map_err(|e| warp::reject::custom(InvalidLogQuery(e)))?;
+    let level = query
+        .level
+        .as_deref()
+        .unwrap_or("trace")
+        .parse::<tracing::Level>()
+        .map_err(|e| warp::reject::custom(InvalidLogQuery(e.to_string())))?;
+    Ok(recent(since, level))
+}
+
+/// Embedded web UI file, compressed as the client allows and revalidated by ETag.
+pub async fn serve_asset(
+    tail: warp::path::Tail,
+    accept_encoding: Option<String>,
+    if_none_match: Option<String>,
+) -> Result<warp::reply::Response, Rejection> {
+    let asset = web::find(tail.as_str()).ok_or_else(warp::reject::not_found)?;
+    let response = warp::http::Response::builder()
+        .header("etag", asset.etag)
+        .header("cache-control", "no-cache")
+        .header("vary", "accept-encoding");
+    if if_none_match.is_some_and(|tags| asset.matches(&tags)) {
+        return Ok(response
+            .status(StatusCode::NOT_MODIFIED)
+            .body(hyper::Body::empty())
+            .unwrap());
+    }
+    let (body, encoding) = asset.encode(accept_encoding.as_deref());
+    let response = response.header("content-type", asset.content_type);
+    let response = match encoding {
+        Some(encoding) => response.header("content-encoding", encoding),
+        None => response,
+    };
+    Ok(response.body(body.into()).unwrap())
+}
+
+pub async fn get_openapi() -> Result<impl warp::Reply, Infallible> {
+    Ok(warp::reply::json(&ApiDoc::openapi()))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/files",
+    tag = "files",
+    summary = "List media files",
+    responses(
+        (status = 200, description = "All media files", body = [MediaFile]),
+    )
+)]
 pub async fn get_files(state: StateMutex) -> Result<impl warp::Reply, Infallible> {
     let state = state.lock().await;
     Ok(warp::reply::json(&state.files))
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/v1/files/{id}",
+    tag = "files",
+    summary = "Get a media file",
+    params(("id" = u32, Path, description = "File id")),
+    responses(
+        (status = 200, description = "The file", body = MediaFile),
+        (status = 404, description = "File not found", body = ErrorResponse),
+    )
+)]
+pub async fn get_file(id: u32, state: StateMutex) -> Result<impl warp::Reply, Rejection> {
+    let state = state.lock().await;
+    Ok(warp::reply::json(find_file(&state, id)?))
+}
+
+#[utoipa::path(
+    patch,
+    path = "/api/v1/files/{id}",
+    tag = "files",
+    summary = "Update a media file",
+    params(("id" = u32, Path, description = "File id")),
+    request_body = UpdateFile,
+    responses(
+        (status = 200, description = "The updated file", body = MediaFile),
+        (status = 400, description = "Invalid request", body = ErrorResponse),
+        (status = 404, description = "File not found", body = ErrorResponse),
+    )
+)]
+pub async fn update_file(
+    id: u32,
+    update: UpdateFile,
+    state: StateMutex,
+) -> Result<impl warp::Reply, Rejection> {
+    let mut state = state.lock().await;
+    find_file(&state, id)?;
+    if let Some(normalize) = update.normalize {
+        state.set_normalize(id, normalize);
+    }
+    Ok(warp::reply::json(find_file(&state, id)?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/uploads",
+    tag = "files",
+    summary = "Uploads in progress",
+    responses(
+        (status = 200, description = "Partial uploads", body = [UploadProgress]),
+    )
+)]
+pub async fn get_uploads(state: StateMutex) -> Result<impl warp::Reply, Infallible> {
+    let state = state.lock().await;
+    let uploads = state
+        .uploads
+        .iter()
+        .map(ActiveUpload::progress)
+        .collect::<Vec<UploadProgress>>();
+    Ok(warp::reply::json(&uploads))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/schedules",
+    tag = "schedules",
+    summary = "List schedules",
+    responses(
+        (status = 200, description = "All schedules", body = [Schedule]),
+    )
+)]
 pub async fn get_schedules(state: StateMutex) -> Result<impl warp::Reply, Infallible> {
     let state = state.lock().await;
     Ok(warp::reply::json(&state.schedules))
 }
 
-pub async fn stop(state: StateMutex, player: PlayerMutex) -> Result<impl warp::Reply, Infallible> {
-    let mut state = state.lock().await;
-    let player = player.lock().await;
-    player.stop();
-    state.status = Status::Idle;
+async fn control(
+    options: ZoneOptions,
+    zones: ZonesMutex,
+    action: fn(&Player),
+) -> Result<(), Rejection> {
+    let zones = zones.lock().await;
+    let players = select_zones(&zones, options)?;
+    players.into_iter().for_each(action);
+    Ok(())
+}
+
+pub async fn stop(options: ZoneOptions, zones: ZonesMutex) -> Result<impl warp::Reply, Rejection> {
+    control(options, zones, Player::stop).await?;
     Ok(StatusCode::OK)
 }
 
-pub async fn pause(state: StateMutex, player: PlayerMutex) -> Result<impl warp::Reply, Infallible> {
-    let mut state = state.lock().await;
-    let player = player.lock().await;
-    player.pause();
-    state.status = Status::Paused;
+pub async fn pause(options: ZoneOptions, zones: ZonesMutex) -> Result<impl warp::Reply, Rejection> {
+    control(options, zones, Player::pause).await?;
     Ok(StatusCode::OK)
 }
 
 pub async fn resume(
+    options: ZoneOptions,
+    zones: ZonesMutex,
+) -> Result<impl warp::Reply, Rejection> {
+    control(options, zones, Player::resume).await?;
+    Ok(StatusCode::OK)
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/playback/{action}",
+    tag = "playback",
+    summary = "Stop, pause or resume playback",
+    params(
+        ("action" = String, Path, description = "`stop`, `pause` or `resume`"),
+        ZoneOptions
+    ),
+    responses(
+        (status = 204, description = "Done"),
+        (status = 400, description = "Unknown zone", body = ErrorResponse),
+    )
+)]
+pub async fn playback_action(
+    action: String,
+    options: ZoneOptions,
+    zones: ZonesMutex,
+) -> Result<impl warp::Reply, Rejection> {
+    let action: fn(&Player) = match action.as_str() {
+        "stop" => Player::stop,
+        "pause" => Player::pause,
+        "resume" => Player::resume,
+        _ => return Err(warp::reject::not_found()),
+    };
+    control(options, zones, action).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn play_file(
+    id: u32,
+    options: PlayOptions,
     state: StateMutex,
-    player: PlayerMutex,
-) -> Result<impl warp::Reply, Infallible> {
-    let mut state = state.lock().await;
-    let player = player.lock().await;
-    player.resume();
-    if player.done() {
-        state.status = Status::Idle;
-    } else {
-        state.status = Status::Running;
+    zones: ZonesMutex,
+) -> Result<(), Rejection> {
+    let state = state.lock().await;
+    let zones = zones.lock().await;
+    check_zone(&zones, options.zone.as_deref())?;
+    let player = zones.get(options.zone.as_deref()).unwrap();
+    for id in [options.pre_roll, Some(id), options.post_roll]
+        .iter()
+        .flatten()
+    {
+        if find_file(&state, *id)?.missing {
+            return Err(warp::reject::custom(MediaMissing(*id)));
+        }
     }
-    Ok(StatusCode::OK)
+    let sequence = state
+        .get_sequence(id, options.pre_roll, options.post_roll)
+        .unwrap();
+    player
+        .play_sequence(&sequence)
+        .map_err(|e| warp::reject::custom(ZoneUnavailable(e)))
 }
 
 pub async fn play(
     id: u32,
+    options: PlayOptions,
     state: StateMutex,
-    player: PlayerMutex,
-) -> Result<impl warp::Reply, Infallible> {
-    let mut state = state.lock().await;
-    let player = player.lock().await;
-    state.status = Status::Running;
-    player.play(state.get_media(id).unwrap());
+    zones: ZonesMutex,
+) -> Result<impl warp::Reply, Rejection> {
+    play_file(id, options, state, zones).await?;
     Ok(StatusCode::OK)
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/v1/playback",
+    tag = "playback",
+    summary = "Play a file",
+    request_body = PlayRequest,
+    responses(
+        (status = 204, description = "Queued for playback"),
+        (status = 400, description = "Unknown zone", body = ErrorResponse),
+        (status = 404, description = "File not found", body = ErrorResponse),
+        (status = 409, description = "The file is missing from the media directory", body = ErrorResponse),
+        (status = 503, description = "The zone is disconnected", body = ErrorResponse),
+    )
+)]
+pub async fn start_playback(
+    request: PlayRequest,
+    state: StateMutex,
+    zones: ZonesMutex,
+) -> Result<impl warp::Reply, Rejection> {
+    let options = PlayOptions {
+        pre_roll: request.pre_roll,
+        post_roll: request.post_roll,
+        zone: request.zone,
+    };
+    play_file(request.file_id, options, state, zones).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
 pub async fn upload_files(
-    mut form: FormData,
+    options: UploadOptions,
+    form: FormData,
+    state: StateMutex,
+) -> Result<impl warp::Reply, Rejection> {
+    receive_files(options, form, state).await?;
+    Ok(StatusCode::OK)
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/files",
+    tag = "files",
+    summary = "Upload media files",
+    params(UploadOptions),
+    request_body(content_type = "multipart/form-data", description = "One or more `file` parts"),
+    responses(
+        (status = 201, description = "The added or replaced files", body = [MediaFile]),
+        (status = 400, description = "Not an audio file", body = ErrorResponse),
+        (status = 409, description = "The file already exists", body = ErrorResponse),
+        (status = 413, description = "The file exceeds the upload limit", body = ErrorResponse),
+        (status = 500, description = "The replaced file could not be deleted from disk", body = ErrorResponse),
+    )
+)]
+pub async fn create_files(
+    options: UploadOptions,
+    form: FormData,
     state: StateMutex,
 ) -> Result<impl warp::Reply, Rejection> {
+    let files = receive_files(options, form, state).await?;
+    Ok(created(&files))
+}
+
+/// Stores the `file` parts of an upload, returning the added or replaced files.
+async fn receive_files(
+    options: UploadOptions,
+    mut form: FormData,
+    state: StateMutex,
+) -> Result<Vec<MediaFile>, Rejection> {
+    let mut files = vec![];
     while let Some(field) = form.try_next().await.map_err(|e| {
-        eprintln!("form error during part processing: {}", e);
+        warn!("form error during part processing: {}", e);
         warp::reject::reject()
     })? {
         let p: Part = field;
         if p.name() == "file" {
-            let content_type = p.content_type();
-            let file_ending = match content_type {
-                Some(file_type) => match file_type {
-                    "audio/mp3" | "audio/mpeg" | "audio" => "mp3",
-                    "audio/ogg" => "ogg",
-                    v => {
-                        eprintln!("invalid file type found: {}", v);
-                        return Err(warp::reject::reject());
-                    }
-                },
-                None => {
-                    eprintln!("file type could not be determined");
-                    return Err(warp::reject::reject());
-                }
-            };
-
             let file_name = match p.filename() {
                 Some(filename) => filename.to_string(),
                 None => {
-                    eprintln!("file name could not be determined");
+                    warn!("file name could not be determined");
                     return Err(warp::reject::reject());
                 }
             };
 
-            let file_name = file_name
-                .strip_suffix(format!(".{}", file_ending).as_str())
-                .ok_or_else(|| {
-                    eprintln!("failed to strip file extension");
-                    warp::reject::reject()
-                })?;
+            let file_name = sanitize_file_name(&file_name);
 
-            let value = p
-                .stream()
-                .try_fold(Vec::new(), |mut vec, data| {
-                    vec.put(data);
-                    async move { Ok(vec) }
-                })
+            let mut upload = PartialUpload::create(&file_name, state.clone())
                 .await
                 .map_err(|e| {
-                    eprintln!("reading file error: {}", e);
+                    error!("error creating upload file: {}", e);
                     warp::reject::reject()
                 })?;
+            let max_size = max_upload_size();
+            let mut stream = p.stream();
+            while let Some(mut data) = stream.try_next().await.map_err(|e| {
+                warn!("reading file error: {}", e);
+                warp::reject::reject()
+            })? {
+                if upload.size() + data.remaining() as u64 > max_size {
+                    warn!("file {} exceeds {} bytes", file_name, max_size);
+                    return Err(warp::reject::custom(FileTooLarge(max_size)));
+                }
+                while data.has_remaining() {
+                    let chunk = data.chunk();
+                    let len = chunk.len();
+                    upload.write(chunk).await.map_err(|e| {
+                        error!("error writing file: {}", e);
+                        warp::reject::reject()
+                    })?;
+                    data.advance(len);
+                }
+            }
 
-            let path = write_file(file_name, file_ending, &value).await;
+            // decoding blocks, keep it off the executor
+            let upload_path = upload.path().to_path_buf();
+            let (format, metadata) = tokio::task::spawn_blocking(move || {
+                let format = probe(&upload_path)?;
+                Ok::<_, String>((format, read_metadata(&upload_path)))
+            })
+            .await
+            .unwrap_or_else(|e| Err(e.to_string()))
+            .map_err(|e| {
+                warn!("invalid file {}: {}", file_name, e);
+                warp::reject::custom(InvalidFile)
+            })?;
 
+            let hash = upload.hash();
             let mut state = state.lock().await;
-            state.add_media(file_name.to_string(), path);
+            let duplicate = match options.conflict {
+                Conflict::Replace => state
+                    .find_duplicate(&hash)
+                    .or_else(|| state.find_by_name(&file_name)),
+                _ => state.find_duplicate(&hash),
+            }
+            .map(|f| f.id);
+            match (duplicate, &options.conflict) {
+                (Some(id), Conflict::Reject) => {
+                    warn!("file {} already exists as {}", file_name, id);
+                    return Err(warp::reject::custom(DuplicateFile(id)));
+                }
+                (Some(id), Conflict::Replace) => {
+                    let path = upload.persist(format.extension()).await.map_err(|e| {
+                        error!("error storing file: {}", e);
+                        warp::reject::reject()
+                    })?;
+                    let old_path = owned_path(&state, state.get_media(id).unwrap());
+                    if let Some(old_path) = old_path {
+                        if let Err(e) = delete_from_disk(&old_path).await {
+                            discard(Path::new(&path)).await;
+                            return Err(e);
+                        }
+                    }
+                    state.replace_media(id, file_name, path, hash, metadata);
+                    files.push(state.get_media(id).unwrap().clone());
+                }
+                (_, _) => {
+                    let path = upload.persist(format.extension()).await.map_err(|e| {
+                        error!("error storing file: {}", e);
+                        warp::reject::reject()
+                    })?;
+                    files.push(state.add_media(file_name, path, hash, metadata));
+                }
+            }
         }
     }
 
+    Ok(files)
+}
+
+fn read_metadata(path: &Path) -> Option<MediaMetadata> {
+    let mut metadata = extract_metadata(path)
+        .map_err(|e| warn!("error extracting metadata of {}: {}", path.display(), e))
+        .ok()?;
+    metadata.loudness = read_loudness(path);
+    Some(metadata)
+}
+
+fn read_loudness(path: &Path) -> Option<f64> {
+    analyze_loudness(path)
+        .map_err(|e| warn!("error analyzing loudness of {}: {}", path.display(), e))
+        .ok()
+}
+
+pub async fn analyze(state: StateMutex) -> Result<impl warp::Reply, Infallible> {
+    analyze_all(state).await;
     Ok(StatusCode::OK)
 }
 
-pub async fn delete_file(
+#[utoipa::path(
+    post,
+    path = "/api/v1/files/analyze",
+    tag = "files",
+    summary = "Measure the loudness of all files",
+    responses(
+        (status = 204, description = "Loudness stored with every file"),
+    )
+)]
+pub async fn analyze_files(state: StateMutex) -> Result<impl warp::Reply, Infallible> {
+    analyze_all(state).await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/files/scan",
+    tag = "files",
+    summary = "Sync the library with the media directory",
+    responses(
+        (status = 200, description = "Audio files added and files found missing", body = Reconciliation),
+        (status = 500, description = "The media directory could not be read", body = ErrorResponse),
+    )
+)]
+pub async fn scan_files(state: StateMutex) -> Result<impl warp::Reply, Rejection> {
+    let reconciliation = library::reconcile(state).await.map_err(|e| {
+        error!("{}", e);
+        warp::reject::custom(ScanFailed(e))
+    })?;
+    Ok(warp::reply::json(&reconciliation))
+}
+
+async fn analyze_all(state: StateMutex) {
+    let files = state.lock().await.files.clone();
+    for file in files.into_iter().filter(|f| f.kind.is_file()) {
+        info!("analyzing loudness of: {}", file.path);
+        let loudness = tokio::task::spawn_blocking(move || read_loudness(Path::new(&file.path)))
+            .await
+            .unwrap_or_default();
+        state.lock().await.set_loudness(file.id, loudness);
+    }
+}
+
+pub async fn set_normalize(
+    content: (u32, bool),
+    state: StateMutex,
+) -> Result<impl warp::Reply, Rejection> {
+    let (id, normalize) = content;
+    let mut state = state.lock().await;
+    find_file(&state, id)?;
+    state.set_normalize(id, normalize);
+    Ok(StatusCode::OK)
+}
+
+pub async fn trim_file(
+    content: (u32, f64, Option<f64>),
+    state: StateMutex,
+) -> Result<impl warp::Reply, Rejection> {
+    let (id, start, end) = content;
+    let file = edit_file(id, trim(start, end), state).await?;
+    Ok(warp::reply::json(&file))
+}
+
+pub async fn strip_silence(id: u32, state: StateMutex) -> Result<impl warp::Reply, Rejection> {
+    let file = edit_file(id, silence_stripping(), state).await?;
+    Ok(warp::reply::json(&file))
+}
+
+pub async fn transcode(id: u32, state: StateMutex) -> Result<impl warp::Reply, Rejection> {
+    let file = edit_file(id, Edit::default(), state).await?;
+    Ok(warp::reply::json(&file))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/files/{id}/trim",
+    tag = "files",
+    summary = "Trim a file into a new version",
+    params(("id" = u32, Path, description = "File id")),
+    request_body = TrimRequest,
+    responses(
+        (status = 201, description = "The new version", body = MediaFile),
+        (status = 400, description = "Invalid request", body = ErrorResponse),
+        (status = 404, description = "File not found", body = ErrorResponse),
+    )
+)]
+pub async fn create_trimmed(
+    id: u32,
+    request: TrimRequest,
+    state: StateMutex,
+) -> Result<impl warp::Reply, Rejection> {
+    let file = edit_file(id, trim(request.start, request.end), state).await?;
+    Ok(created(&file))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/files/{id}/{edit}",
+    tag = "files",
+    summary = "Strip silence from or transcode a file into a new version",
+    params(
+        ("id" = u32, Path, description = "File id"),
+        ("edit" = String, Path, description = "`strip-silence` or `transcode`")
+    ),
+    responses(
+        (status = 201, description = "The new version", body = MediaFile),
+        (status = 400, description = "Invalid request", body = ErrorResponse),
+        (status = 404, description = "File not found", body = ErrorResponse),
+    )
+)]
+pub async fn create_version(
     id: u32,
+    edit: String,
     state: StateMutex,
+) -> Result<impl warp::Reply, Rejection> {
+    let edit = match edit.as_str() {
+        "strip-silence" => silence_stripping(),
+        "transcode" => Edit::default(),
+        _ => return Err(warp::reject::not_found()),
+    };
+    let file = edit_file(id, edit, state).await?;
+    Ok(created(&file))
+}
+
+fn trim(start: f64, end: Option<f64>) -> Edit {
+    Edit {
+        start: Some(start),
+        end,
+        ..Default::default()
+    }
+}
+
+fn silence_stripping() -> Edit {
+    Edit {
+        strip_silence: true,
+        ..Default::default()
+    }
+}
+
+async fn edit_file(id: u32, edit: Edit, state: StateMutex) -> Result<MediaFile, Rejection> {
+    edit.validate().map_err(|e| {
+        warn!("invalid edit: {}", e);
+        warp::reject::custom(InvalidEdit(e))
+    })?;
+    let source = find_file(&*state.lock().await, id)?.clone();
+    if !source.kind.is_file() {
+        return Err(warp::reject::custom(InvalidEdit(format!(
+            "{} is not a file",
+            source.name
+        ))));
+    }
+    let (scratch, target) = render_paths().await.map_err(|e| {
+        error!("error editing file {}: {}", id, e);
+        warp::reject::custom(InvalidEdit(e))
+    })?;
+    info!(
+        "rendering {} of {} to: {}",
+        edit.label(),
+        source.path,
+        target.display()
+    );
+
+    let rendered = scratch.clone();
+    let label = edit.label();
+    let result = tokio::task::spawn_blocking(move || {
+        render(Path::new(&source.path), &rendered, &edit)?;
+        let hash = hash_file(&rendered).map_err(|e| e.to_string())?;
+        Ok::<_, String>((hash, read_metadata(&rendered)))
+    })
+    .await
+    .unwrap_or_else(|e| Err(e.to_string()));
+    let (hash, metadata) = match result {
+        Ok(rendered) => rendered,
+        Err(e) => {
+            error!("error editing file {}: {}", id, e);
+            if scratch.exists() {
+                discard(&scratch).await;
+            }
+            return Err(warp::reject::custom(InvalidEdit(e)));
+        }
+    };
+
+    // moved into place under the lock, so a rescan finds it already stored
+    let mut state = state.lock().await;
+    if let Err(e) = tokio::fs::rename(&scratch, &target).await {
+        error!("error storing edit of file {}: {}", id, e);
+        drop(state);
+        discard(&scratch).await;
+        return Err(warp::reject::custom(InvalidEdit(e.to_string())));
+    }
+    let file = state.add_version(
+        id,
+        format!("{} ({})", source.name, label),
+        target.to_string_lossy().to_string(),
+        hash,
+        metadata,
+    );
+    Ok(file)
+}
+
+pub async fn announce(
+    announcement: Announcement,
+    state: StateMutex,
+    zones: ZonesMutex,
+    scheduler: SchedulerMutex,
+) -> Result<impl warp::Reply, Rejection> {
+    let file = make_announcement(announcement, state, zones, scheduler).await?;
+    Ok(warp::reply::json(&file))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/announcements",
+    tag = "files",
+    summary = "Synthesize an announcement",
+    request_body = Announcement,
+    responses(
+        (status = 201, description = "The spoken announcement", body = MediaFile),
+        (status = 400, description = "Invalid request", body = ErrorResponse),
+        (status = 500, description = "Speech synthesis failed", body = ErrorResponse),
+        (status = 503, description = "The zone is disconnected", body = ErrorResponse),
+    )
+)]
+pub async fn create_announcement(
+    announcement: Announcement,
+    state: StateMutex,
+    zones: ZonesMutex,
     scheduler: SchedulerMutex,
 ) -> Result<impl warp::Reply, Rejection> {
+    let file = make_announcement(announcement, state, zones, scheduler).await?;
+    Ok(created(&file))
+}
+
+async fn make_announcement(
+    announcement: Announcement,
+    state: StateMutex,
+    zones: ZonesMutex,
+    scheduler: SchedulerMutex,
+) -> Result<MediaFile, Rejection> {
+    let text = announcement.text.trim();
+    if text.is_empty() {
+        return Err(warp::reject::custom(InvalidAnnouncement(
+            "announcement text is empty".to_string(),
+        )));
+    }
+    for roll in [announcement.pre_roll, announcement.post_roll]
+        .iter()
+        .flatten()
+    {
+        if state.lock().await.get_media(*roll).is_none() {
+            return Err(warp::reject::custom(InvalidAnnouncement(format!(
+                "file {} not found",
+                roll
+            ))));
+        }
+    }
+    {
+        let zones = zones.lock().await;
+        check_zone(&zones, announcement.zone.as_deref())?;
+        let player = zones.get(announcement.zone.as_deref()).unwrap();
+        if announcement.play && player.status() == Status::Disconnected {
+            return Err(warp::reject::custom(ZoneUnavailable(format!(
+                "zone {} is disconnected",
+                player.zone().name
+            ))));
+        }
+    }
+    let schedule = announcement
+        .schedule
+        .map(|definition| definition.compile())
+        .transpose()
+        .map_err(|e| {
+            warn!("invalid schedule: {}", e);
+            warp::reject::custom(InvalidSchedule(e))
+        })?;
+
+    let (scratch, target) = render_paths().await.map_err(|e| {
+        error!("error synthesizing announcement: {}", e);
+        warp::reject::custom(SpeechFailed(e))
+    })?;
+    let rendered = async {
+        synthesize(&tts_command(), text, &scratch).await?;
+        let speech = scratch.clone();
+        tokio::task::spawn_blocking(move || {
+            probe(&speech)?;
+            let hash = hash_file(&speech).map_err(|e| e.to_string())?;
+            Ok((hash, read_metadata(&speech)))
+        })
+        .await
+        .unwrap_or_else(|e| Err(e.to_string()))
+    }
+    .await;
+    let (hash, metadata) = match rendered {
+        Ok(rendered) => rendered,
+        Err(e) => {
+            error!("error synthesizing announcement: {}", e);
+            if scratch.exists() {
+                discard(&scratch).await;
+            }
+            return Err(warp::reject::custom(SpeechFailed(e)));
+        }
+    };
+
+    let name: String = text.chars().take(48).collect();
     let mut state = state.lock().await;
-    let mut scheduler = scheduler.lock().await;
-    let file_locator = state.get_media(id).unwrap().path.clone();
-    remove_file(file_locator.as_str()).await;
-    state.remove_media(id);
-    let schedules_to_disable = state
-        .schedules
+    // the rolls may have been deleted while the engine was running
+    if let Some(roll) = [announcement.pre_roll, announcement.post_roll]
         .iter()
-        .filter(|s| s.file_id == id)
-        .filter(|s| s.activity == Activity::Active)
-        .collect::<Vec<&Schedule>>();
-    for s in schedules_to_disable.iter() {
-        scheduler.remove(s.id).await;
+        .flatten()
+        .find(|roll| state.get_media(**roll).is_none())
+    {
+        drop(state);
+        discard(&scratch).await;
+        return Err(warp::reject::custom(InvalidAnnouncement(format!(
+            "file {} not found",
+            roll
+        ))));
+    }
+    if let Err(e) = tokio::fs::rename(&scratch, &target).await {
+        error!("error storing announcement: {}", e);
+        drop(state);
+        discard(&scratch).await;
+        return Err(warp::reject::custom(SpeechFailed(e.to_string())));
+    }
+    let file = state.add_media(
+        format!("announcement: {}", name),
+        target.to_string_lossy().to_string(),
+        hash,
+        metadata,
+    );
+    if announcement.play {
+        let zones = zones.lock().await;
+        let player = zones.get(announcement.zone.as_deref()).unwrap();
+        match state.get_sequence(file.id, announcement.pre_roll, announcement.post_roll) {
+            Some(sequence) => {
+                if let Err(e) = player.play_sequence(&sequence) {
+                    error!("error playing announcement: {}", e);
+                }
+            }
+            None => error!("error playing announcement: file not found"),
+        }
+    }
+    if let Some((schedule, spec)) = schedule {
+        let schedule_id = state.add_schedule(file.id, schedule, spec);
+        state.set_rolls(schedule_id, announcement.pre_roll, announcement.post_roll);
+        state.set_zone(schedule_id, announcement.zone);
+        drop(state);
+        let mut scheduler = scheduler.lock().await;
+        scheduler.add(schedule_id).await;
     }
+    Ok(file)
+}
+
+pub async fn add_generator(
+    content: (String, Generator),
+    state: StateMutex,
+) -> Result<impl warp::Reply, Rejection> {
+    let (name, generator) = content;
+    let file = insert_generator(CreateGenerator { name, generator }, state).await?;
+    Ok(warp::reply::json(&file))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/generators",
+    tag = "files",
+    summary = "Add a generated signal",
+    request_body = CreateGenerator,
+    responses(
+        (status = 201, description = "The generator", body = MediaFile),
+        (status = 400, description = "Invalid request", body = ErrorResponse),
+    )
+)]
+pub async fn create_generator(
+    request: CreateGenerator,
+    state: StateMutex,
+) -> Result<impl warp::Reply, Rejection> {
+    let file = insert_generator(request, state).await?;
+    Ok(created(&file))
+}
+
+async fn insert_generator(
+    request: CreateGenerator,
+    state: StateMutex,
+) -> Result<MediaFile, Rejection> {
+    request.generator.validate().map_err(|e| {
+        warn!("invalid generator: {}", e);
+        warp::reject::custom(InvalidGenerator(e))
+    })?;
+    let mut state = state.lock().await;
+    Ok(state.add_generator(request.name, request.generator))
+}
+
+pub async fn add_stream(
+    content: (String, String),
+    state: StateMutex,
+) -> Result<impl warp::Reply, Rejection> {
+    let (name, url) = content;
+    let file = insert_stream(CreateStream { name, url }, state).await?;
+    Ok(warp::reply::json(&file))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/streams",
+    tag = "files",
+    summary = "Add an audio stream",
+    request_body = CreateStream,
+    responses(
+        (status = 201, description = "The stream", body = MediaFile),
+        (status = 400, description = "Invalid request", body = ErrorResponse),
+    )
+)]
+pub async fn create_stream(
+    request: CreateStream,
+    state: StateMutex,
+) -> Result<impl warp::Reply, Rejection> {
+    let file = insert_stream(request, state).await?;
+    Ok(created(&file))
+}
+
+async fn insert_stream(request: CreateStream, state: StateMutex) -> Result<MediaFile, Rejection> {
+    validate_url(&request.url).map_err(|e| {
+        warn!("invalid stream: {}", e);
+        warp::reject::custom(InvalidStream(e))
+    })?;
+    let mut state = state.lock().await;
+    Ok(state.add_stream(request.name, request.url))
+}
+
+pub async fn delete_file(
+    id: u32,
+    options: DeleteOptions,
+    state: StateMutex,
+    scheduler: SchedulerMutex,
+) -> Result<impl warp::Reply, Rejection> {
+    remove_media(id, options, state, scheduler).await?;
     Ok(StatusCode::OK)
 }
 
+#[utoipa::path(
+    delete,
+    path = "/api/v1/files/{id}",
+    tag = "files",
+    summary = "Delete a media file",
+    params(("id" = u32, Path, description = "File id"), DeleteOptions),
+    responses(
+        (status = 204, description = "Deleted"),
+        (status = 400, description = "More than one of force, cascade and reassign", body = ErrorResponse),
+        (status = 404, description = "File not found", body = ErrorResponse),
+        (status = 409, description = "Schedules use the file", body = ErrorResponse),
+        (status = 500, description = "The file could not be deleted from disk", body = ErrorResponse),
+    )
+)]
+pub async fn remove_file_resource(
+    id: u32,
+    options: DeleteOptions,
+    state: StateMutex,
+    scheduler: SchedulerMutex,
+) -> Result<impl warp::Reply, Rejection> {
+    remove_media(id, options, state, scheduler).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn remove_media(
+    id: u32,
+    options: DeleteOptions,
+    state: StateMutex,
+    scheduler: SchedulerMutex,
+) -> Result<(), Rejection> {
+    let chosen = [options.force, options.cascade, options.reassign.is_some()];
+    if chosen.iter().filter(|chosen| **chosen).count() > 1 {
+        return Err(warp::reject::custom(InvalidDeletion(
+            "choose one of force, cascade and reassign".to_string(),
+        )));
+    }
+    if options.reassign == Some(id) {
+        return Err(warp::reject::custom(InvalidDeletion(format!(
+            "file {} can't replace itself",
+            id
+        ))));
+    }
+    // held throughout, so no schedule gets activated while its file goes away
+    let mut scheduler = scheduler.lock().await;
+    let (dependents, to_deactivate, path) = {
+        let state = state.lock().await;
+        let path = owned_path(&state, find_file(&state, id)?);
+        if let Some(target) = options.reassign {
+            find_file(&state, target)?;
+        }
+        let dependents = state.dependent_schedules(id);
+        let to_deactivate = dependents
+            .iter()
+            .filter(|s| s.activity == Activity::Active)
+            // with force, schedules only losing a pre- or post-roll keep running
+            .filter(|s| options.cascade || s.file_id == id)
+            .map(|s| s.id)
+            .collect::<Vec<u32>>();
+        let dependents = dependents.iter().map(|s| s.id).collect::<Vec<u32>>();
+        (dependents, to_deactivate, path)
+    };
+    if !dependents.is_empty() && !chosen.contains(&true) {
+        warn!("file {} is used by schedules {:?}", id, dependents);
+        return Err(warp::reject::custom(FileInUse(id, dependents)));
+    }
+    // first, so failing leaves the entry and its schedules untouched
+    if let Some(path) = path {
+        delete_from_disk(&path).await?;
+    }
+    if options.reassign.is_none() {
+        // the scheduler takes the state lock itself
+        for schedule_id in to_deactivate {
+            scheduler.remove(schedule_id).await;
+        }
+    }
+
+    let mut state = state.lock().await;
+    match options.reassign {
+        Some(target) => {
+            info!("moving schedules {:?} to file {}", dependents, target);
+            state.reassign_file(id, target);
+        }
+        None if options.cascade => {
+            info!("deleting schedules {:?} with file {}", dependents, id);
+            for schedule_id in dependents {
+                state.remove_schedule(schedule_id);
+            }
+        }
+        None if options.force => state.clear_rolls(id),
+        None => {}
+    }
+    state.remove_media(id);
+    Ok(())
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/files/{id}/content",
+    tag = "files",
+    summary = "Download a media file",
+    params(("id" = u32, Path, description = "File id")),
+    responses(
+        (status = 301, description = "Redirect to the file under `/export`"),
+        (status = 404, description = "File not found", body = ErrorResponse),
+    )
+)]
 pub async fn download_file(id: u32, state: StateMutex) -> Result<impl warp::Reply, Rejection> {
     let state = state.lock().await;
-    let file_name = state.get_media(id).unwrap().name.clone();
-    println!("redirrecting to download file: {}", file_name);
-    let url = format!("/export/{}", file_name);
+    let file = find_file(&state, id)?;
+    if !file.kind.is_file() {
+        return Err(warp::reject::not_found());
+    }
+    let path = Path::new(&file.path);
+    let file_name = path.file_name().unwrap().to_string_lossy();
+    // stored under a generated name, so the original one is passed along
+    let name = match path.extension() {
+        Some(extension)
+            if !file
+                .name
+                .ends_with(&format!(".{}", extension.to_string_lossy())) =>
+        {
+            format!("{}.{}", file.name, extension.to_string_lossy())
+        }
+        _ => file.name.clone(),
+    };
+    info!("redirrecting to download file: {}", file_name);
+    let url = format!("/export/{}?name={}", file_name, percent_encode(&name));
     let uri = url.parse::<Uri>().expect("valid URI");
     Ok(warp::redirect(uri))
 }
 
 pub async fn add_schedule(
-    content: (u32, String),
+    content: NewSchedule,
     state: StateMutex,
+    zones: ZonesMutex,
 ) -> Result<impl warp::Reply, Rejection> {
-    let (file_id, schedule) = content;
+    let NewSchedule(file_id, definition, zone) = content;
+    let (schedule, spec) = definition.compile().map_err(|e| {
+        warn!("invalid schedule: {}", e);
+        warp::reject::custom(InvalidSchedule(e))
+    })?;
+    check_zone(&*zones.lock().await, zone.as_deref())?;
     let mut state = state.lock().await;
-    state.add_schedule(file_id, schedule);
+    check_files(&state, [None, Some(file_id), None])?;
+    let id = state.add_schedule(file_id, schedule, spec);
+    state.set_zone(id, zone);
     Ok(StatusCode::OK)
 }
 
 pub async fn edit_schedule(
-    content: (u32, u32, String),
+    content: EditedSchedule,
     state: StateMutex,
+    zones: ZonesMutex,
     scheduler: SchedulerMutex,
 ) -> Result<impl warp::Reply, Rejection> {
-    let (id, file_id, schedule) = content;
+    let EditedSchedule(id, file_id, definition, zone) = content;
+    let (schedule, spec) = compile_schedule(definition)?;
+    check_zone(&*zones.lock().await, zone.as_deref())?;
+    let mut locked = state.lock().await;
+    let current = find_schedule(&locked, id)?;
+    check_files(
+        &locked,
+        [current.pre_roll, Some(file_id), current.post_roll],
+    )?;
+    locked.edit_schedule(id, file_id, schedule, spec);
+    if zone.is_some() {
+        locked.set_zone(id, zone);
+    }
+    drop(locked);
+    apply_activity(id, None, state, scheduler).await;
+    Ok(StatusCode::OK)
+}
+
+pub async fn set_rolls(
+    content: (u32, Option<u32>, Option<u32>),
+    state: StateMutex,
+) -> Result<impl warp::Reply, Rejection> {
+    let (id, pre_roll, post_roll) = content;
     let mut state = state.lock().await;
-    state.edit_schedule(id, file_id, schedule);
-    if state.get_schedule(id).unwrap().activity == Activity::Active {
-        let mut scheduler = scheduler.lock().await;
-        scheduler.reschedule(id).await;
+    find_schedule(&state, id)?;
+    for roll in [pre_roll, post_roll].iter().flatten() {
+        if state.get_media(*roll).is_none() {
+            return Err(warp::reject::custom(InvalidSchedule(format!(
+                "file {} not found",
+                roll
+            ))));
+        }
     }
+    state.set_rolls(id, pre_roll, post_roll);
+    Ok(StatusCode::OK)
+}
+
+pub async fn set_zone(
+    content: (u32, Option<String>),
+    state: StateMutex,
+    zones: ZonesMutex,
+) -> Result<impl warp::Reply, Rejection> {
+    let (id, zone) = content;
+    check_zone(&*zones.lock().await, zone.as_deref())?;
+    let mut state = state.lock().await;
+    find_schedule(&state, id)?;
+    state.set_zone(id, zone);
     Ok(StatusCode::OK)
 }
 
@@ -206,23 +1433,392 @@ pub async fn remove_schedule(
     state: StateMutex,
     scheduler: SchedulerMutex,
 ) -> Result<impl warp::Reply, Rejection> {
-    let mut state = state.lock().await;
-    if state.get_schedule(id).unwrap().activity == Activity::Active {
-        let mut scheduler = scheduler.lock().await;
-        scheduler.remove(id).await;
-    }
-    state.remove_schedule(id);
+    drop_schedule(id, state, scheduler).await?;
     Ok(StatusCode::OK)
 }
 
-pub async fn activate(id: u32, scheduler: SchedulerMutex) -> Result<impl warp::Reply, Rejection> {
+#[utoipa::path(
+    delete,
+    path = "/api/v1/schedules/{id}",
+    tag = "schedules",
+    summary = "Delete a schedule",
+    params(("id" = u32, Path, description = "Schedule id")),
+    responses(
+        (status = 204, description = "Deleted"),
+        (status = 404, description = "Schedule not found", body = ErrorResponse),
+    )
+)]
+pub async fn delete_schedule(
+    id: u32,
+    state: StateMutex,
+    scheduler: SchedulerMutex,
+) -> Result<impl warp::Reply, Rejection> {
+    drop_schedule(id, state, scheduler).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn drop_schedule(
+    id: u32,
+    state: StateMutex,
+    scheduler: SchedulerMutex,
+) -> Result<(), Rejection> {
+    find_schedule(&*state.lock().await, id)?;
+    apply_activity(id, Some(false), state.clone(), scheduler).await;
+    state.lock().await.remove_schedule(id);
+    Ok(())
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/schedules/{id}",
+    tag = "schedules",
+    summary = "Get a schedule",
+    params(("id" = u32, Path, description = "Schedule id")),
+    responses(
+        (status = 200, description = "The schedule", body = Schedule),
+        (status = 404, description = "Schedule not found", body = ErrorResponse),
+    )
+)]
+pub async fn get_schedule(id: u32, state: StateMutex) -> Result<impl warp::Reply, Rejection> {
+    let state = state.lock().await;
+    Ok(warp::reply::json(find_schedule(&state, id)?))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/schedules",
+    tag = "schedules",
+    summary = "Create a schedule",
+    request_body = ScheduleRequest,
+    responses(
+        (status = 201, description = "The schedule", body = Schedule),
+        (status = 400, description = "Invalid request", body = ErrorResponse),
+    )
+)]
+pub async fn create_schedule(
+    request: ScheduleRequest,
+    state: StateMutex,
+    zones: ZonesMutex,
+    scheduler: SchedulerMutex,
+) -> Result<impl warp::Reply, Rejection> {
+    check_references(&request, &state, &zones).await?;
+    let (schedule, spec) = compile_schedule(request.schedule)?;
+    let id = {
+        let mut state = state.lock().await;
+        let id = state.add_schedule(request.file_id, schedule, spec);
+        state.set_rolls(id, request.pre_roll, request.post_roll);
+        state.set_zone(id, request.zone);
+        id
+    };
+    apply_activity(id, Some(request.active), state.clone(), scheduler).await;
+    let state = state.lock().await;
+    Ok(created(find_schedule(&state, id)?))
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/v1/schedules/{id}",
+    tag = "schedules",
+    summary = "Replace a schedule",
+    params(("id" = u32, Path, description = "Schedule id")),
+    request_body = ScheduleRequest,
+    responses(
+        (status = 200, description = "The schedule", body = Schedule),
+        (status = 400, description = "Invalid request", body = ErrorResponse),
+        (status = 404, description = "Schedule not found", body = ErrorResponse),
+    )
+)]
+pub async fn replace_schedule(
+    id: u32,
+    request: ScheduleRequest,
+    state: StateMutex,
+    zones: ZonesMutex,
+    scheduler: SchedulerMutex,
+) -> Result<impl warp::Reply, Rejection> {
+    find_schedule(&*state.lock().await, id)?;
+    check_references(&request, &state, &zones).await?;
+    let (schedule, spec) = compile_schedule(request.schedule)?;
+    {
+        let mut state = state.lock().await;
+        state.edit_schedule(id, request.file_id, schedule, spec);
+        state.set_rolls(id, request.pre_roll, request.post_roll);
+        state.set_zone(id, request.zone);
+    }
+    apply_activity(id, Some(request.active), state.clone(), scheduler).await;
+    let state = state.lock().await;
+    Ok(warp::reply::json(find_schedule(&state, id)?))
+}
+
+#[utoipa::path(
+    patch,
+    path = "/api/v1/schedules/{id}",
+    tag = "schedules",
+    summary = "Activate or deactivate a schedule",
+    params(("id" = u32, Path, description = "Schedule id")),
+    request_body = UpdateSchedule,
+    responses(
+        (status = 200, description = "The schedule", body = Schedule),
+        (status = 400, description = "Invalid request", body = ErrorResponse),
+        (status = 404, description = "Schedule not found", body = ErrorResponse),
+    )
+)]
+pub async fn update_schedule(
+    id: u32,
+    update: UpdateSchedule,
+    state: StateMutex,
+    scheduler: SchedulerMutex,
+) -> Result<impl warp::Reply, Rejection> {
+    {
+        let state = state.lock().await;
+        let schedule = find_schedule(&state, id)?;
+        if update.active == Some(true) {
+            check_files(&state, schedule.file_ids())?;
+        }
+    }
+    if let Some(active) = update.active {
+        apply_activity(id, Some(active), state.clone(), scheduler).await;
+    }
+    let state = state.lock().await;
+    Ok(warp::reply::json(find_schedule(&state, id)?))
+}
+
+fn compile_schedule(
+    definition: ScheduleDefinition,
+) -> Result<(String, Option<ScheduleSpec>), Rejection> {
+    definition.compile().map_err(|e| {
+        warn!("invalid schedule: {}", e);
+        warp::reject::custom(InvalidSchedule(e))
+    })
+}
+
+/// Checks that the files and zone a schedule refers to exist.
+async fn check_references(
+    request: &ScheduleRequest,
+    state: &StateMutex,
+    zones: &ZonesMutex,
+) -> Result<(), Rejection> {
+    check_zone(&*zones.lock().await, request.zone.as_deref())?;
+    check_files(
+        &*state.lock().await,
+        [request.pre_roll, Some(request.file_id), request.post_roll],
+    )
+}
+
+/// Checks that the files a schedule plays exist, a forced delete can leave
+/// inactive schedules behind whose file is gone.
+fn check_files(state: &State, ids: [Option<u32>; 3]) -> Result<(), Rejection> {
+    match ids
+        .iter()
+        .flatten()
+        .find(|id| state.get_media(**id).is_none())
+    {
+        Some(id) => Err(warp::reject::custom(InvalidSchedule(format!(
+            "file {} not found",
+            id
+        )))),
+        None => Ok(()),
+    }
+}
+
+/// Brings the jobs of a schedule in line with `active`, or with its current
+/// activity if `None`, rescheduling it if it stays active so edits apply.
+/// Must be called without holding the state lock, the scheduler takes it.
+async fn apply_activity(
+    id: u32,
+    active: Option<bool>,
+    state: StateMutex,
+    scheduler: SchedulerMutex,
+) {
     let mut scheduler = scheduler.lock().await;
-    scheduler.add(id).await;
+    let was_active = state
+        .lock()
+        .await
+        .get_schedule(id)
+        .is_some_and(|s| s.activity == Activity::Active);
+    match (was_active, active.unwrap_or(was_active)) {
+        (false, true) => scheduler.add(id).await,
+        (true, false) => scheduler.remove(id).await,
+        (true, true) => scheduler.reschedule(id).await,
+        (false, false) => {}
+    }
+}
+
+pub async fn activate(
+    id: u32,
+    state: StateMutex,
+    scheduler: SchedulerMutex,
+) -> Result<impl warp::Reply, Rejection> {
+    {
+        let state = state.lock().await;
+        check_files(&state, find_schedule(&state, id)?.file_ids())?;
+    }
+    apply_activity(id, Some(true), state, scheduler).await;
     Ok(StatusCode::OK)
 }
 
-pub async fn deactivate(id: u32, scheduler: SchedulerMutex) -> Result<impl warp::Reply, Rejection> {
-    let mut scheduler = scheduler.lock().await;
-    scheduler.remove(id).await;
+pub async fn deactivate(
+    id: u32,
+    state: StateMutex,
+    scheduler: SchedulerMutex,
+) -> Result<impl warp::Reply, Rejection> {
+    find_schedule(&*state.lock().await, id)?;
+    apply_activity(id, Some(false), state, scheduler).await;
     Ok(StatusCode::OK)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scheduler::Scheduler;
+    use crate::utils::test_store;
+    use serde_json::json;
+    use std::sync::Arc;
+    use tokio::sync::Mutex;
+
+    fn file(id: u32, path: &str) -> MediaFile {
+        serde_json::from_value(json!({ "id": id, "name": format!("file {}", id), "path": path }))
+            .unwrap()
+    }
+
+    async fn scheduler(state: &StateMutex) -> SchedulerMutex {
+        let zones = Arc::new(Mutex::new(Zones::open(vec![], -18.0)));
+        Arc::new(Mutex::new(Scheduler::new(zones, state.clone()).await))
+    }
+
+    #[tokio::test]
+    async fn deleting_missing_files() {
+        let _store = test_store().await;
+        let mut flagged = file(0, &format!("{}/flagged.mp3", MEDIA_PATH));
+        flagged.missing = true;
+        // gone from disk before the library noticed
+        let unnoticed = file(1, &format!("{}/unnoticed.mp3", MEDIA_PATH));
+        let state: StateMutex = Arc::new(Mutex::new(State::new(
+            vec![flagged, unnoticed],
+            vec![Schedule::new(0, 0, "0 0 8 * * *".to_string(), None)],
+        )));
+        let scheduler = scheduler(&state).await;
+
+        let cascade = DeleteOptions {
+            cascade: true,
+            ..Default::default()
+        };
+        remove_media(0, cascade, state.clone(), scheduler.clone())
+            .await
+            .unwrap();
+        remove_media(1, DeleteOptions::default(), state.clone(), scheduler)
+            .await
+            .unwrap();
+        let state = state.lock().await;
+        assert!(state.files.is_empty());
+        assert!(state.schedules.is_empty());
+    }
+
+    #[tokio::test]
+    async fn reuploading_a_file_stored_before_hashes() {
+        let _store = test_store().await;
+        let path = format!("{}/chime.mp3", MEDIA_PATH);
+        std::fs::copy("media/tripple_kill.mp3", &path).unwrap();
+        let resource = Path::new(RESOURCE_PATH);
+        let entry = format!(r#"[{{"id":0,"name":"chime","path":"{}"}}]"#, path);
+        std::fs::write(resource.join("media.json"), entry).unwrap();
+        std::fs::write(resource.join("schedules.json"), "[]").unwrap();
+        let state: StateMutex = Arc::new(Mutex::new(State::load()));
+        let zones = Arc::new(Mutex::new(Zones::open(vec![], -18.0)));
+        let scheduler = scheduler(&state).await;
+        let routes = crate::routes::routes(state.clone(), zones, scheduler);
+
+        let mut body = b"--boundary\r\n\
+            content-disposition: form-data; name=\"file\"; filename=\"copy.mp3\"\r\n\
+            content-type: audio/mpeg\r\n\r\n"
+            .to_vec();
+        body.extend(std::fs::read(&path).unwrap());
+        body.extend(b"\r\n--boundary--\r\n");
+        let response = warp::test::request()
+            .method("POST")
+            .path("/api/v1/files")
+            .header("content-type", "multipart/form-data; boundary=boundary")
+            .body(body)
+            .reply(&routes)
+            .await;
+
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        assert_eq!(state.lock().await.files.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn legacy_schedules_need_an_existing_file() {
+        let _store = test_store().await;
+        let state: StateMutex = Arc::new(Mutex::new(State::new(
+            vec![file(0, &format!("{}/chime.mp3", MEDIA_PATH))],
+            vec![Schedule::new(0, 0, "0 0 8 * * *".to_string(), None)],
+        )));
+        let zones = Arc::new(Mutex::new(Zones::open(vec![], -18.0)));
+        let scheduler = scheduler(&state).await;
+        let routes = crate::routes::routes(state.clone(), zones, scheduler);
+
+        for (path, body) in [
+            ("/schedule", json!([5, "0 0 9 * * *"])),
+            ("/reschedule", json!([0, 5, "0 0 9 * * *"])),
+        ] {
+            let response = warp::test::request()
+                .method("POST")
+                .path(path)
+                .json(&body)
+                .reply(&routes)
+                .await;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", path);
+            assert_eq!(response.body(), "file 5 not found");
+        }
+        let state = state.lock().await;
+        assert_eq!(state.schedules.len(), 1);
+        assert_eq!(state.schedules[0].file_id, 0);
+    }
+
+    #[tokio::test]
+    async fn events_are_served_under_the_api() {
+        let state: StateMutex = Arc::new(Mutex::new(State::new(vec![], vec![])));
+        let zones = Arc::new(Mutex::new(Zones::open(vec![], -18.0)));
+        let scheduler = scheduler(&state).await;
+        let routes = crate::routes::routes(state, zones, scheduler);
+
+        let response = warp::test::request()
+            .path("/api/v1/events?level=warn")
+            .reply(&routes)
+            .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(serde_json::from_slice::<Vec<LogEntry>>(response.body()).is_ok());
+
+        let response = warp::test::request()
+            .path("/api/v1/events?level=loud")
+            .reply(&routes)
+            .await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let error: ErrorResponse = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(error.status, 400);
+    }
+
+    #[tokio::test]
+    async fn failed_deletion_changes_nothing() {
+        let _store = test_store().await;
+        // a directory can't be removed as a file
+        let path = format!("{}/directory.mp3", MEDIA_PATH);
+        std::fs::create_dir(&path).unwrap();
+        let state: StateMutex = Arc::new(Mutex::new(State::new(
+            vec![file(0, &path)],
+            vec![Schedule::new(0, 0, "0 0 8 * * *".to_string(), None)],
+        )));
+        let scheduler = scheduler(&state).await;
+
+        let cascade = DeleteOptions {
+            cascade: true,
+            ..Default::default()
+        };
+        let rejection = remove_media(0, cascade, state.clone(), scheduler)
+            .await
+            .unwrap_err();
+        assert!(rejection.find::<DeletionFailed>().is_some());
+        let state = state.lock().await;
+        assert_eq!(state.files.len(), 1);
+        assert_eq!(state.schedules.len(), 1);
+    }
+}
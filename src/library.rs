@@ -0,0 +1,225 @@
+//! Compares `media.json` with the audio files actually in `MEDIA_PATH`.
+
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::HashSet;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+use crate::consts::MEDIA_PATH;
+use crate::loudness::analyze_loudness;
+use crate::metadata::{extract_metadata, MediaMetadata};
+use crate::models::{MediaFile, Reconciliation};
+use crate::utils::{hash_file, sanitize_file_name};
+use crate::StateMutex;
+
+/// Extensions of the formats the decoders handle, other files are left alone.
+const AUDIO_EXTENSIONS: &[&str] = &["mp3", "wav", "flac", "ogg", "oga", "m4a", "mp4", "aac"];
+
+/// How long the media directory has to be quiet before the watcher rescans it,
+/// so files still being copied aren't picked up half-written.
+const SETTLE: Duration = Duration::from_secs(2);
+
+/// Difference between the stored files and the media directory.
+#[derive(Debug, Default)]
+pub struct Scan {
+    /// Audio files in the media directory no entry points at.
+    pub untracked: Vec<PathBuf>,
+    /// Ids of entries whose file is gone.
+    pub missing: Vec<u32>,
+}
+
+/// A file found on disk, ready to be added to the library.
+pub struct Adoption {
+    pub name: String,
+    pub path: String,
+    pub hash: String,
+    pub metadata: MediaMetadata,
+}
+
+pub fn scan(files: &[MediaFile]) -> io::Result<Scan> {
+    let tracked: HashSet<&Path> = files
+        .iter()
+        .filter(|f| f.kind.is_file())
+        .map(|f| Path::new(&f.path))
+        .collect();
+    Ok(Scan {
+        untracked: media_files()?
+            .into_iter()
+            .filter(|path| is_audio(path) && !tracked.contains(path.as_path()))
+            .collect(),
+        missing: missing(files),
+    })
+}
+
+fn missing(files: &[MediaFile]) -> Vec<u32> {
+    files
+        .iter()
+        .filter(|f| f.kind.is_file() && !Path::new(&f.path).is_file())
+        .map(|f| f.id)
+        .collect()
+}
+
+/// Adds untracked audio files to the library and flags the entries whose file is gone.
+pub async fn reconcile(state: StateMutex) -> Result<Reconciliation, String> {
+    let files = state.lock().await.files.clone();
+    let adopted = tokio::task::spawn_blocking(move || {
+        let scan = scan(&files).map_err(|e| format!("error scanning {}: {}", MEDIA_PATH, e))?;
+        Ok::<_, String>(
+            scan.untracked
+                .iter()
+                .filter_map(|path| {
+                    adopt(path)
+                        .map_err(|e| warn!("not adding {}: {}", path.display(), e))
+                        .ok()
+                })
+                .collect::<Vec<Adoption>>(),
+        )
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+
+    let mut state = state.lock().await;
+    let mut added = vec![];
+    for file in adopted {
+        // stored by an upload or edit in the meantime
+        if state.files.iter().any(|f| f.path == file.path) {
+            continue;
+        }
+        info!("adding untracked file: {}", file.path);
+        added.push(state.add_media(file.name, file.path, file.hash, Some(file.metadata)));
+    }
+    let missing = missing(&state.files);
+    Ok(Reconciliation {
+        added,
+        missing: state.flag_missing(&missing),
+    })
+}
+
+/// Reconciles the library whenever the media directory changes, until the watcher is dropped.
+pub fn watch(state: StateMutex) -> notify::Result<RecommendedWatcher> {
+    // events carry absolute paths, whatever form `MEDIA_PATH` has
+    let root = Path::new(MEDIA_PATH).canonicalize()?;
+    let watched = root.clone();
+    let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+    let mut watcher =
+        notify::recommended_watcher(move |event: notify::Result<Event>| match event {
+            Ok(event) if is_relevant(&event, &watched) => {
+                let _ = sender.send(());
+            }
+            Ok(_) => {}
+            Err(e) => warn!("error watching {}: {}", MEDIA_PATH, e),
+        })?;
+    watcher.watch(&root, RecursiveMode::Recursive)?;
+    info!("Watching {} for changes", MEDIA_PATH);
+    tokio::spawn(async move {
+        while receiver.recv().await.is_some() {
+            while let Ok(Some(())) = tokio::time::timeout(SETTLE, receiver.recv()).await {}
+            if let Err(e) = reconcile(state.clone()).await {
+                error!("{}", e);
+            }
+        }
+    });
+    Ok(watcher)
+}
+
+/// Changes to visible files, reads and the `.upload` directory don't count.
+fn is_relevant(event: &Event, root: &Path) -> bool {
+    !matches!(event.kind, EventKind::Access(_))
+        && event.paths.iter().any(|path| !is_hidden(path, root))
+}
+
+fn is_hidden(path: &Path, root: &Path) -> bool {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .components()
+        .any(|c| matches!(c, Component::Normal(name) if name.to_string_lossy().starts_with('.')))
+}
+
+pub fn is_audio(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| AUDIO_EXTENSIONS.contains(&e.to_ascii_lowercase().as_str()))
+}
+
+/// Reads an untracked file, failing if it isn't audio.
+pub fn adopt(path: &Path) -> Result<Adoption, String> {
+    let metadata = extract_metadata(path)?;
+    let hash = hash_file(path).map_err(|e| e.to_string())?;
+    Ok(Adoption {
+        name: sanitize_file_name(&path.to_string_lossy()),
+        path: path.to_string_lossy().to_string(),
+        hash,
+        metadata: MediaMetadata {
+            loudness: analyze_loudness(path).ok(),
+            ..metadata
+        },
+    })
+}
+
+/// Files in the media directory, skipping hidden entries such as the `.upload` directory.
+pub fn media_files() -> io::Result<Vec<PathBuf>> {
+    let mut files = vec![];
+    collect(Path::new(MEDIA_PATH), &mut files)?;
+    files.sort();
+    Ok(files)
+}
+
+fn collect(dir: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().starts_with('.'))
+        {
+            continue;
+        }
+        if path.is_dir() {
+            collect(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notify::event::{CreateKind, ModifyKind};
+
+    #[test]
+    fn hidden_below_the_root_only() {
+        let root = Path::new("/srv/.rustyplayer/media");
+        assert!(!is_hidden(&root.join("chime.mp3"), root));
+        assert!(!is_hidden(&root.join("jingles/chime.mp3"), root));
+        assert!(is_hidden(&root.join(".upload/1234.part"), root));
+        assert!(is_hidden(&root.join(".chime.mp3"), root));
+    }
+
+    #[test]
+    fn relevant_events() {
+        let root = Path::new("/srv/.rustyplayer/media");
+        let event = |kind, path: &str| Event::new(kind).add_path(root.join(path));
+        assert!(is_relevant(
+            &event(EventKind::Create(CreateKind::File), "chime.mp3"),
+            root
+        ));
+        assert!(!is_relevant(
+            &event(EventKind::Create(CreateKind::File), ".upload/1234.wav"),
+            root
+        ));
+        assert!(!is_relevant(
+            &event(
+                EventKind::Access(notify::event::AccessKind::Any),
+                "chime.mp3"
+            ),
+            root
+        ));
+        assert!(is_relevant(
+            &event(EventKind::Modify(ModifyKind::Any), "chime.mp3"),
+            root
+        ));
+    }
+}
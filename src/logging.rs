@@ -0,0 +1,165 @@
+use chrono::{DateTime, Utc};
+use serde_json::{Map, Value};
+use std::collections::VecDeque;
+use std::fmt::Debug;
+use std::io::IsTerminal;
+use std::sync::Mutex;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::layer::{Context, SubscriberExt};
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, EnvFilter, Layer};
+
+use crate::models::{ErrorRecord, LogEntry, LogSpan};
+use crate::utils::{log_format, log_journald, log_level};
+
+/// Entries kept in memory for `/logs`.
+const BUFFER_SIZE: usize = 2000;
+
+static BUFFER: Mutex<VecDeque<(Level, DateTime<Utc>, LogEntry)>> = Mutex::new(VecDeque::new());
+static LAST_ERROR: Mutex<Option<ErrorRecord>> = Mutex::new(None);
+
+/// Sets up logging to stdout, as text or JSON, or to journald,
+/// and always into the in-memory buffer.
+pub fn init() {
+    let filter = EnvFilter::try_new(log_level()).unwrap_or_else(|e| {
+        eprintln!("invalid log level {}: {}", log_level(), e);
+        EnvFilter::new("info")
+    });
+    let journald = if log_journald() {
+        tracing_journald::layer()
+            .map_err(|e| eprintln!("journald unavailable, logging to stdout: {}", e))
+            .ok()
+    } else {
+        None
+    };
+    let json = log_format() == "json";
+    let ansi = std::io::stdout().is_terminal();
+    let text_output = (journald.is_none() && !json).then(|| fmt::layer().with_ansi(ansi));
+    let json_output = (journald.is_none() && json).then(|| fmt::layer().json());
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(text_output)
+        .with(json_output)
+        .with(journald)
+        .with(MemoryLayer)
+        .init();
+}
+
+/// Buffered entries newer than `since` and at least as severe as `level`.
+pub fn recent(since: Option<DateTime<Utc>>, level: Level) -> Vec<LogEntry> {
+    BUFFER
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|(l, time, _)| *l <= level && since.is_none_or(|since| *time > since))
+        .map(|(_, _, entry)| entry.clone())
+        .collect()
+}
+
+pub fn last_error() -> Option<ErrorRecord> {
+    LAST_ERROR.lock().unwrap().clone()
+}
+
+#[derive(Default)]
+struct FieldVisitor {
+    message: String,
+    fields: Map<String, Value>,
+}
+
+impl FieldVisitor {
+    fn insert(&mut self, field: &Field, value: Value) {
+        if field.name() == "message" {
+            self.message = match value {
+                Value::String(message) => message,
+                value => value.to_string(),
+            };
+        } else {
+            self.fields.insert(field.name().to_string(), value);
+        }
+    }
+}
+
+impl Visit for FieldVisitor {
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.insert(field, value.into());
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.insert(field, value.into());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.insert(field, value.into());
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.insert(field, value.into());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        self.insert(field, format!("{:?}", value).into());
+    }
+}
+
+/// Fields of a span, stored in its extensions when it is created.
+struct SpanFields(Map<String, Value>);
+
+struct MemoryLayer;
+
+impl<S> Layer<S> for MemoryLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let mut visitor = FieldVisitor::default();
+        attrs.record(&mut visitor);
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(SpanFields(visitor.fields));
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let mut visitor = FieldVisitor::default();
+        event.record(&mut visitor);
+        let spans = ctx
+            .event_scope(event)
+            .map(|scope| {
+                scope
+                    .from_root()
+                    .map(|span| LogSpan {
+                        name: span.name().to_string(),
+                        fields: span
+                            .extensions()
+                            .get::<SpanFields>()
+                            .map(|f| f.0.clone())
+                            .unwrap_or_default(),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        let level = *event.metadata().level();
+        let time = Utc::now();
+        let entry = LogEntry {
+            time: time.to_rfc3339(),
+            level: level.to_string(),
+            target: event.metadata().target().to_string(),
+            message: visitor.message,
+            fields: visitor.fields,
+            spans,
+        };
+        if level == Level::ERROR {
+            *LAST_ERROR.lock().unwrap() = Some(ErrorRecord {
+                time: entry.time.clone(),
+                message: entry.message.clone(),
+            });
+        }
+        let mut buffer = BUFFER.lock().unwrap();
+        if buffer.len() == BUFFER_SIZE {
+            buffer.pop_front();
+        }
+        buffer.push_back((level, time, entry));
+    }
+}
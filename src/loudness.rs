@@ -0,0 +1,46 @@
+use ebur128::{EbuR128, Mode};
+use rodio::{Decoder, Source};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+/// Frames handed to the meter at a time.
+const CHUNK_FRAMES: usize = 4096;
+
+/// Highest gain normalization may apply, so near silent clips don't get their noise boosted.
+pub const MAX_GAIN: f64 = 4.0;
+
+/// Measures integrated loudness (EBU R128) of the whole file in LUFS.
+pub fn analyze_loudness(path: &Path) -> Result<f64, String> {
+    let file = File::open(path).map_err(|e| e.to_string())?;
+    let decoder = Decoder::new(BufReader::new(file)).map_err(|e| e.to_string())?;
+    let channels = decoder.channels();
+    let mut meter =
+        EbuR128::new(channels as u32, decoder.sample_rate(), Mode::I).map_err(|e| e.to_string())?;
+
+    let frame_size = channels as usize;
+    let chunk = CHUNK_FRAMES * frame_size;
+    let mut buffer = Vec::with_capacity(chunk);
+    for sample in decoder {
+        buffer.push(sample);
+        if buffer.len() == chunk {
+            meter.add_frames_i16(&buffer).map_err(|e| e.to_string())?;
+            buffer.clear();
+        }
+    }
+    let complete = buffer.len() - buffer.len() % frame_size;
+    meter
+        .add_frames_i16(&buffer[..complete])
+        .map_err(|e| e.to_string())?;
+
+    let loudness = meter.loudness_global().map_err(|e| e.to_string())?;
+    if !loudness.is_finite() {
+        return Err("file is silent".to_string());
+    }
+    Ok(loudness)
+}
+
+/// Linear gain that brings a clip measured at `loudness` to `target` LUFS.
+pub fn normalization_gain(loudness: f64, target: f64) -> f32 {
+    10f64.powf((target - loudness) / 20.0).min(MAX_GAIN) as f32
+}
@@ -1,35 +1,100 @@
-use rodio::OutputStream;
+use clap::Parser;
+use sd_notify::NotifyState;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::Mutex;
+use tracing::{error, info};
 use warp::Filter;
 
+mod admin;
+mod api;
+mod cli;
+mod client;
 mod consts;
+mod diagnostics;
+mod dto;
+mod editing;
+mod format;
+mod generator;
 mod handlers;
+mod library;
+mod logging;
+mod loudness;
+mod metadata;
+mod metrics;
 mod models;
+mod openapi;
+mod output;
 mod player;
 mod routes;
+mod schedule_spec;
 mod scheduler;
+mod service;
+mod stream;
+mod tts;
+mod upload;
 mod utils;
+mod web;
+mod zones;
 
-use player::Player;
+use cli::{Cli, Command};
 use scheduler::Scheduler;
+use zones::Zones;
 
 pub type StateMutex = Arc<Mutex<models::State>>;
-pub type PlayerMutex = Arc<Mutex<Player>>;
+pub type ZonesMutex = Arc<Mutex<Zones>>;
 pub type SchedulerMutex = Arc<Mutex<Scheduler>>;
 
 const PORT: u16 = 5001;
+/// How long playback fades out when the service stops.
+const FADE_OUT: Duration = Duration::from_secs(1);
+
+fn main() {
+    match Cli::parse().command.unwrap_or(Command::Serve) {
+        Command::Serve => serve(),
+        Command::Client(args) => client::run(args),
+        Command::Admin(command) => admin::run(command),
+    }
+}
 
 #[tokio::main(flavor = "multi_thread")]
-async fn main() {
+async fn serve() {
+    logging::init();
+    diagnostics::init();
+    // running unlocked would let admin commands rewrite the store underneath
+    let _lock = match utils::lock_store() {
+        Ok(lock) => lock,
+        Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+            error!("the store is in use by another instance");
+            std::process::exit(1);
+        }
+        Err(e) => {
+            error!("could not lock the store: {}", e);
+            std::process::exit(1);
+        }
+    };
     let state = models::State::load();
     let statemutex: StateMutex = Arc::new(Mutex::new(state));
+    let scanned_state = statemutex.clone();
+    tokio::spawn(async move {
+        if let Err(e) = library::reconcile(scanned_state).await {
+            error!("{}", e);
+        }
+    });
+    let _watcher = utils::watch_media()
+        .then(|| library::watch(statemutex.clone()))
+        .and_then(|watcher| {
+            watcher
+                .map_err(|e| error!("could not watch {}: {}", consts::MEDIA_PATH, e))
+                .ok()
+        });
 
-    let (_stream, stream_handle) = OutputStream::try_default().unwrap();
-    let player: Player = Player::new(&stream_handle);
-    let playermutex: PlayerMutex = Arc::new(Mutex::new(player));
+    let zone_configs = zones::parse_zones(&utils::zones()).unwrap();
+    let zones = Zones::open(zone_configs, utils::target_loudness());
+    let zonesmutex: ZonesMutex = Arc::new(Mutex::new(zones));
 
-    let mut scheduler = Scheduler::new(playermutex.clone(), statemutex.clone()).await;
+    info!("Schedules run in time zone {}", utils::time_zone());
+    let mut scheduler = Scheduler::new(zonesmutex.clone(), statemutex.clone()).await;
     scheduler.load().await;
     scheduler.start().await;
     let scheduler_mutex: SchedulerMutex = Arc::new(Mutex::new(scheduler));
@@ -46,15 +111,28 @@ async fn main() {
             "Access-Control-Request-Headers",
             "Access-Control-Allow-Origin",
         ])
-        .allow_methods(vec!["POST", "GET"]);
+        .allow_methods(vec!["POST", "GET", "PUT", "PATCH", "DELETE"]);
     let routes = routes::routes(
         statemutex.clone(),
-        playermutex.clone(),
+        zonesmutex.clone(),
         scheduler_mutex.clone(),
     )
-    .with(cors);
+    .with(cors)
+    .with(warp::log::custom(metrics::record_request))
+    .with(warp::trace::request());
+
+    let (_, server) = warp::serve(routes)
+        .bind_with_graceful_shutdown(([0, 0, 0, 0], PORT), service::shutdown_signal());
+    info!("Starting server on port {}", PORT);
+    info!("http://127.0.0.1:{}/", PORT);
+    service::notify(NotifyState::Ready);
+    service::spawn_watchdog(statemutex.clone());
+    server.await;
 
-    println!("Starting server on port {}", PORT);
-    println!("http://127.0.0.1:{}/", PORT);
-    warp::serve(routes).run(([0, 0, 0, 0], PORT)).await;
+    info!("Shutting down");
+    service::notify(NotifyState::Stopping);
+    scheduler_mutex.lock().await.stop().await;
+    zonesmutex.lock().await.fade_out(FADE_OUT).await;
+    statemutex.lock().await.flush();
+    info!("Stopped");
 }
@@ -0,0 +1,105 @@
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::path::Path;
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::{MetadataOptions, MetadataRevision, StandardTagKey};
+use symphonia::core::probe::Hint;
+use symphonia::core::units::TimeBase;
+use utoipa::ToSchema;
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, ToSchema)]
+pub struct MediaMetadata {
+    /// Duration in seconds.
+    pub duration: Option<f64>,
+    pub sample_rate: Option<u32>,
+    pub channels: Option<u16>,
+    /// Average bitrate in bits per second.
+    pub bitrate: Option<u32>,
+    pub size: u64,
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    /// Integrated loudness in LUFS.
+    #[serde(default)]
+    pub loudness: Option<f64>,
+}
+
+impl MediaMetadata {
+    fn read_tags(&mut self, revision: &MetadataRevision) {
+        for tag in revision.tags() {
+            match tag.std_key {
+                Some(StandardTagKey::TrackTitle) if self.title.is_none() => {
+                    self.title = Some(tag.value.to_string())
+                }
+                Some(StandardTagKey::Artist) if self.artist.is_none() => {
+                    self.artist = Some(tag.value.to_string())
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+pub fn extract_metadata(path: &Path) -> Result<MediaMetadata, String> {
+    let file = File::open(path).map_err(|e| e.to_string())?;
+    let mut metadata = MediaMetadata {
+        size: file.metadata().map_err(|e| e.to_string())?.len(),
+        ..Default::default()
+    };
+
+    let mut hint = Hint::new();
+    if let Some(extension) = path.extension() {
+        hint.with_extension(&extension.to_string_lossy());
+    }
+    let stream = MediaSourceStream::new(Box::new(file), Default::default());
+    let mut probed = symphonia::default::get_probe()
+        .format(
+            &hint,
+            stream,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )
+        .map_err(|e| e.to_string())?;
+
+    if let Some(revision) = probed.metadata.get().as_ref().and_then(|m| m.current()) {
+        metadata.read_tags(revision);
+    }
+    if let Some(revision) = probed.format.metadata().current() {
+        metadata.read_tags(revision);
+    }
+
+    let track = probed
+        .format
+        .default_track()
+        .ok_or("file contains no audio track")?;
+    let track_id = track.id;
+    let params = track.codec_params.clone();
+    metadata.sample_rate = params.sample_rate;
+    metadata.channels = params.channels.map(|c| c.count() as u16);
+
+    let time_base = params
+        .time_base
+        .or_else(|| params.sample_rate.map(|rate| TimeBase::new(1, rate)));
+    let frames = match params.n_frames {
+        Some(frames) => Some(frames),
+        // not every container stores its length, count the packets instead
+        None => {
+            let mut frames = 0;
+            while let Ok(packet) = probed.format.next_packet() {
+                if packet.track_id() == track_id {
+                    frames += packet.dur;
+                }
+            }
+            Some(frames)
+        }
+    };
+    if let (Some(time_base), Some(frames)) = (time_base, frames) {
+        let time = time_base.calc_time(frames);
+        let duration = time.seconds as f64 + time.frac;
+        if duration > 0.0 {
+            metadata.duration = Some(duration);
+            metadata.bitrate = Some((metadata.size as f64 * 8.0 / duration) as u32);
+        }
+    }
+    Ok(metadata)
+}
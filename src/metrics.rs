@@ -0,0 +1,321 @@
+use chrono::{DateTime, Duration, Utc};
+use prometheus::core::Collector;
+use prometheus::{
+    Counter, CounterVec, Encoder, Histogram, HistogramOpts, IntCounter, IntCounterVec, IntGauge,
+    IntGaugeVec, Opts, Registry, TextEncoder,
+};
+use rodio::Source;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::LazyLock;
+
+use crate::utils::time_zone;
+
+pub struct Metrics {
+    registry: Registry,
+    pub schedules_fired: IntCounter,
+    pub schedules_failed: IntCounter,
+    /// Firings skipped because the schedule was removed or deactivated meanwhile.
+    pub schedules_suppressed: IntCounter,
+    pub playback_seconds: CounterVec,
+    pub http_requests: IntCounterVec,
+    pub upload_bytes: IntCounter,
+    pub active_schedules: IntGauge,
+    pub queue_length: IntGaugeVec,
+    pub storage_bytes: IntGauge,
+    pub trigger_latency: Histogram,
+}
+
+pub static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::new);
+
+impl Metrics {
+    fn new() -> Metrics {
+        let metrics = Metrics {
+            registry: Registry::new_custom(Some("rustyplayer".to_string()), None).unwrap(),
+            schedules_fired: IntCounter::new(
+                "schedules_fired_total",
+                "Schedule firings that started playback",
+            )
+            .unwrap(),
+            schedules_failed: IntCounter::new(
+                "schedules_failed_total",
+                "Schedule firings that could not play their media",
+            )
+            .unwrap(),
+            schedules_suppressed: IntCounter::new(
+                "schedules_suppressed_total",
+                "Schedule firings skipped because the schedule is gone or inactive",
+            )
+            .unwrap(),
+            playback_seconds: CounterVec::new(
+                Opts::new("playback_seconds_total", "Seconds of audio played"),
+                &["zone"],
+            )
+            .unwrap(),
+            http_requests: IntCounterVec::new(
+                Opts::new("http_requests_total", "HTTP requests handled"),
+                &["route", "status"],
+            )
+            .unwrap(),
+            upload_bytes: IntCounter::new("upload_bytes_total", "Bytes received by uploads")
+                .unwrap(),
+            active_schedules: IntGauge::new("active_schedules", "Schedules currently active")
+                .unwrap(),
+            queue_length: IntGaugeVec::new(
+                Opts::new("queue_length", "Entries queued for playback"),
+                &["zone"],
+            )
+            .unwrap(),
+            storage_bytes: IntGauge::new("storage_bytes", "Size of the media directory").unwrap(),
+            trigger_latency: Histogram::with_opts(
+                HistogramOpts::new(
+                    "trigger_latency_seconds",
+                    "Delay between the scheduled time and the start of playback",
+                )
+                .buckets(vec![0.01, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0]),
+            )
+            .unwrap(),
+        };
+        let collectors: Vec<Box<dyn Collector>> = vec![
+            Box::new(metrics.schedules_fired.clone()),
+            Box::new(metrics.schedules_failed.clone()),
+            Box::new(metrics.schedules_suppressed.clone()),
+            Box::new(metrics.playback_seconds.clone()),
+            Box::new(metrics.http_requests.clone()),
+            Box::new(metrics.upload_bytes.clone()),
+            Box::new(metrics.active_schedules.clone()),
+            Box::new(metrics.queue_length.clone()),
+            Box::new(metrics.storage_bytes.clone()),
+            Box::new(metrics.trigger_latency.clone()),
+        ];
+        for collector in collectors {
+            metrics.registry.register(collector).unwrap();
+        }
+        metrics
+    }
+
+    /// Renders all metrics in the Prometheus text format.
+    pub fn render(&self) -> String {
+        let mut buffer = vec![];
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buffer)
+            .unwrap();
+        String::from_utf8(buffer).unwrap()
+    }
+}
+
+/// First path segments of the routes, requests are counted by these.
+const ROUTES: &[&str] = &[
+    "",
+    "index.html",
+    "assets",
+    "docs",
+    "openapi.json",
+    "export",
+    "health",
+    "ready",
+    "diagnostics",
+    "metrics",
+    "logs",
+    "status",
+    "zones",
+    "devices",
+    "files",
+    "uploads",
+    "upload",
+    "generator",
+    "stream",
+    "delete",
+    "analyze",
+    "normalize",
+    "trim",
+    "strip_silence",
+    "transcode",
+    "announce",
+    "download",
+    "stop",
+    "pause",
+    "resume",
+    "play",
+    "schedules",
+    "schedule",
+    "reschedule",
+    "rolls",
+    "zone",
+    "remove",
+    "activate",
+    "deactivate",
+];
+
+/// Resources under `/api/v1`.
+const API_ROUTES: &[&str] = &[
+    "files",
+    "uploads",
+    "generators",
+    "streams",
+    "announcements",
+    "schedules",
+    "status",
+    "zones",
+    "devices",
+    "playback",
+    "events",
+];
+
+/// Counts a finished request, labelled by its route, e.g. `/export` or
+/// `/api/v1/files`. Anything else is `other`, so neither files under
+/// `/export` nor random paths create a series each.
+pub fn record_request(info: warp::log::Info) {
+    METRICS
+        .http_requests
+        .with_label_values(&[&route_label(info.path()), info.status().as_str()])
+        .inc();
+}
+
+fn route_label(path: &str) -> String {
+    let mut segments = path.trim_start_matches('/').split('/');
+    let route = segments.next().unwrap_or_default();
+    if route == "api" {
+        return match (segments.next(), segments.next()) {
+            (Some("v1"), Some(resource)) if API_ROUTES.contains(&resource) => {
+                format!("/api/v1/{}", resource)
+            }
+            _ => "other".to_string(),
+        };
+    }
+    match ROUTES.contains(&route) {
+        true => format!("/{}", route),
+        false => "other".to_string(),
+    }
+}
+
+/// Time since the most recent occurrence of `expression`, for a job that
+/// was triggered by it and started playing at `started`.
+pub fn trigger_latency(expression: &str, started: DateTime<Utc>) -> Option<f64> {
+    let schedule = cron::Schedule::from_str(expression).ok()?;
+    // evaluated in the zone the scheduler runs the expression in
+    let started = started.with_timezone(&time_zone());
+    let scheduled = schedule
+        .after(&(started - Duration::minutes(10)))
+        .take_while(|time| *time <= started)
+        .last()?;
+    (started - scheduled)
+        .to_std()
+        .ok()
+        .map(|latency| latency.as_secs_f64())
+}
+
+pub fn directory_size(path: &Path) -> u64 {
+    let Ok(entries) = std::fs::read_dir(path) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .map(|entry| match entry.metadata() {
+            Ok(metadata) if metadata.is_dir() => directory_size(&entry.path()),
+            Ok(metadata) => metadata.len(),
+            Err(_) => 0,
+        })
+        .sum()
+}
+
+/// Passes a source through, adding the time it played to `playback_seconds`.
+pub struct Metered<S> {
+    source: S,
+    counter: Counter,
+    pending: f64,
+    samples: u32,
+}
+
+impl<S: Source> Metered<S>
+where
+    S::Item: rodio::Sample,
+{
+    pub fn new(source: S, zone: &str) -> Metered<S> {
+        Metered {
+            source,
+            counter: METRICS.playback_seconds.with_label_values(&[zone]),
+            pending: 0.0,
+            samples: 0,
+        }
+    }
+}
+
+impl<S: Source> Iterator for Metered<S>
+where
+    S::Item: rodio::Sample,
+{
+    type Item = S::Item;
+
+    fn next(&mut self) -> Option<S::Item> {
+        let sample = self.source.next()?;
+        let rate = self.source.sample_rate() as f64 * self.source.channels() as f64;
+        if rate > 0.0 {
+            self.pending += 1.0 / rate;
+        }
+        self.samples += 1;
+        // flushed in batches, the counter is shared with the HTTP thread
+        if self.samples == 4410 {
+            self.counter.inc_by(self.pending);
+            self.pending = 0.0;
+            self.samples = 0;
+        }
+        Some(sample)
+    }
+}
+
+impl<S: Source> Source for Metered<S>
+where
+    S::Item: rodio::Sample,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        self.source.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.source.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.source.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<std::time::Duration> {
+        self.source.total_duration()
+    }
+}
+
+impl<S> Drop for Metered<S> {
+    fn drop(&mut self) {
+        self.counter.inc_by(self.pending);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn requests_are_labelled_by_route() {
+        assert_eq!(route_label("/"), "/");
+        assert_eq!(route_label("/assets/app.js"), "/assets");
+        assert_eq!(route_label("/export/0b7e.mp3"), "/export");
+        assert_eq!(route_label("/schedules"), "/schedules");
+        assert_eq!(route_label("/api/v1/files/3/content"), "/api/v1/files");
+        assert_eq!(route_label("/api/v1/playback/stop"), "/api/v1/playback");
+    }
+
+    #[test]
+    fn unknown_paths_share_a_label() {
+        for path in [
+            "/wp-login.php",
+            "/.env",
+            "/api",
+            "/api/v1",
+            "/api/v1/nope",
+            "/api/v2/files",
+        ] {
+            assert_eq!(route_label(path), "other", "{}", path);
+        }
+    }
+}
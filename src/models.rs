@@ -1,50 +1,241 @@
 use serde::{Deserialize, Serialize};
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use tracing::{debug, info, warn};
+use utoipa::{IntoParams, ToSchema};
 use uuid::Uuid;
 
+use crate::generator::{Generator, SAMPLE_RATE};
+use crate::metadata::{extract_metadata, MediaMetadata};
+use crate::schedule_spec::{split_expressions, ScheduleDefinition, ScheduleSpec};
+use crate::utils::hash_file;
 use crate::utils::load_media_files;
 use crate::utils::load_schedules;
 use crate::utils::write_media_files;
 use crate::utils::write_schedules;
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
 pub struct MediaFile {
     pub id: u32,
     pub name: String,
     pub path: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<MediaMetadata>,
+    /// Whether playback is normalized to the target loudness.
+    #[serde(default = "enabled")]
+    pub normalize: bool,
+    /// File this one was edited from.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_id: Option<u32>,
+    #[serde(default, skip_serializing_if = "MediaKind::is_file")]
+    pub kind: MediaKind,
+    /// Set while the file is gone from the media directory.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub missing: bool,
 }
 
-#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+/// Where the audio of a media file comes from, `path` is only used by `File`.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, ToSchema)]
+pub enum MediaKind {
+    #[default]
+    File,
+    Generator(Generator),
+    /// HTTP(S) audio stream, e.g. an Icecast mount.
+    Stream {
+        url: String,
+    },
+}
+
+impl MediaKind {
+    pub fn is_file(&self) -> bool {
+        *self == MediaKind::File
+    }
+}
+
+fn enabled() -> bool {
+    true
+}
+
+/// What to do when an upload has the same content as an existing file.
+/// `replace` also replaces a file with the same name, `reject` only refuses
+/// identical content so a different recording can share a name.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Conflict {
+    #[default]
+    Reject,
+    Replace,
+    KeepBoth,
+}
+
+/// Text to speak, optionally played or scheduled right away.
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+pub struct Announcement {
+    pub text: String,
+    #[serde(default)]
+    pub play: bool,
+    #[serde(default)]
+    pub schedule: Option<ScheduleDefinition>,
+    #[serde(default)]
+    pub pre_roll: Option<u32>,
+    #[serde(default)]
+    pub post_roll: Option<u32>,
+    #[serde(default)]
+    pub zone: Option<String>,
+}
+
+/// Outcome of comparing the library with the media directory.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, ToSchema)]
+pub struct Reconciliation {
+    /// Audio files found in the media directory and added to the library.
+    pub added: Vec<MediaFile>,
+    /// Files whose audio is gone from the media directory.
+    pub missing: Vec<MediaFile>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+pub struct UploadProgress {
+    pub id: String,
+    pub name: String,
+    pub received: u64,
+}
+
+/// Upload being received, which counts its bytes without taking the state lock.
+#[derive(Debug)]
+pub struct ActiveUpload {
+    pub id: String,
+    pub name: String,
+    pub received: Arc<AtomicU64>,
+}
+
+impl ActiveUpload {
+    pub fn progress(&self) -> UploadProgress {
+        UploadProgress {
+            id: self.id.clone(),
+            name: self.name.clone(),
+            received: self.received.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct UploadOptions {
+    #[serde(default)]
+    pub conflict: Conflict,
+}
+
+/// What deleting a file does with the schedules using it, without any of
+/// these the deletion is refused while there are some.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DeleteOptions {
+    /// Deletes the file anyway, deactivating the schedules playing it and
+    /// dropping it from pre- and post-rolls.
+    #[serde(default)]
+    pub force: bool,
+    /// Deletes the schedules along with the file.
+    #[serde(default)]
+    pub cascade: bool,
+    /// Points the schedules at this file instead.
+    pub reassign: Option<u32>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, ToSchema)]
 pub enum Activity {
     Active,
     Inactive,
 }
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
 pub struct Schedule {
     pub id: u32,
     pub file_id: u32,
     pub schedule: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spec: Option<ScheduleSpec>,
+    /// File played right before `file_id`, e.g. a chime.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pre_roll: Option<u32>,
+    /// File played right after `file_id`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post_roll: Option<u32>,
+    /// Zone the schedule plays in, the default zone if not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub zone: Option<String>,
     pub activity: Activity,
 }
 
 impl Schedule {
-    pub fn new(id: u32, file_id: u32, schedule: String) -> Schedule {
+    pub fn new(id: u32, file_id: u32, schedule: String, spec: Option<ScheduleSpec>) -> Schedule {
         Schedule {
             id,
             file_id,
             schedule,
+            spec,
+            pre_roll: None,
+            post_roll: None,
+            zone: None,
             activity: Activity::Inactive,
         }
     }
+
+    pub fn cron_expressions(&self) -> Vec<&str> {
+        split_expressions(&self.schedule)
+    }
+
+    /// Ids of the files played, in order.
+    pub fn file_ids(&self) -> [Option<u32>; 3] {
+        [self.pre_roll, Some(self.file_id), self.post_roll]
+    }
+
+    pub fn uses(&self, file_id: u32) -> bool {
+        self.file_ids().contains(&Some(file_id))
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct PlayOptions {
+    pub pre_roll: Option<u32>,
+    pub post_roll: Option<u32>,
+    /// Zone to play in, the default zone if not set.
+    pub zone: Option<String>,
 }
 
+#[derive(Clone, Debug, Default, Deserialize, Serialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ZoneOptions {
+    /// Zone to control, all zones if not set.
+    pub zone: Option<String>,
+}
+
+/// Body of `/schedule`: `[file_id, schedule]`, optionally followed by the zone to play in.
+#[derive(Clone, Debug, Deserialize)]
+pub struct NewSchedule(
+    pub u32,
+    pub ScheduleDefinition,
+    #[serde(default)] pub Option<String>,
+);
+
+/// Body of `/reschedule`: `[id, file_id, schedule]`, optionally followed by
+/// the zone to play in, which is left unchanged if not given.
+#[derive(Clone, Debug, Deserialize)]
+pub struct EditedSchedule(
+    pub u32,
+    pub u32,
+    pub ScheduleDefinition,
+    #[serde(default)] pub Option<String>,
+);
+
 pub struct ActiveSchedule {
     pub schedule_id: u32,
-    pub job_id: Uuid,
+    pub job_ids: Vec<Uuid>,
 }
 
-#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, ToSchema)]
 pub enum Status {
     Init,
     Disconnected,
@@ -54,6 +245,102 @@ pub enum Status {
     Paused,
 }
 
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, ToSchema)]
+pub struct StreamStatus {
+    pub url: String,
+    pub title: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, ToSchema)]
+pub struct ZoneStatus {
+    pub zone: String,
+    pub device: Option<String>,
+    pub status: Status,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stream: Option<StreamStatus>,
+}
+
+/// Status of the default zone, followed by every zone.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, ToSchema)]
+pub struct StatusReport {
+    pub status: Status,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stream: Option<StreamStatus>,
+    pub zones: Vec<ZoneStatus>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, ToSchema)]
+pub struct Readiness {
+    pub ready: bool,
+    /// Media and resource directories are present.
+    pub storage: bool,
+    /// Every zone has its output device open.
+    pub audio: bool,
+    pub scheduler: bool,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, ToSchema)]
+pub struct DiskSpace {
+    pub available: u64,
+    pub total: u64,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, ToSchema)]
+pub struct ErrorRecord {
+    pub time: String,
+    pub message: String,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, ToSchema)]
+pub struct Diagnostics {
+    pub version: String,
+    /// Seconds since the service started.
+    pub uptime: u64,
+    /// Space of the file system holding `MEDIA_PATH`.
+    pub disk: Option<DiskSpace>,
+    pub active_schedules: usize,
+    pub active_jobs: usize,
+    pub zones: Vec<ZoneStatus>,
+    pub last_error: Option<ErrorRecord>,
+}
+
+/// Name to save a file under, sent along by the redirect of a download.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct ExportOptions {
+    pub name: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct LogQuery {
+    /// RFC 3339 timestamp, only newer entries are returned.
+    pub since: Option<String>,
+    /// Least severe level to return, e.g. `warn`.
+    pub level: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, ToSchema)]
+pub struct LogSpan {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "serde_json::Map::is_empty")]
+    #[schema(value_type = Object)]
+    pub fields: serde_json::Map<String, serde_json::Value>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, ToSchema)]
+pub struct LogEntry {
+    pub time: String,
+    pub level: String,
+    pub target: String,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "serde_json::Map::is_empty")]
+    #[schema(value_type = Object)]
+    pub fields: serde_json::Map<String, serde_json::Value>,
+    /// Enclosing spans, outermost first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub spans: Vec<LogSpan>,
+}
+
 #[derive(Debug)]
 pub struct IdGenerator {
     id: AtomicUsize,
@@ -76,7 +363,7 @@ impl IdGenerator {
 pub struct State {
     pub files: Vec<MediaFile>,
     pub schedules: Vec<Schedule>,
-    pub status: Status,
+    pub uploads: Vec<ActiveUpload>,
     pub file_id_gen: IdGenerator,
     pub schedule_id_gen: IdGenerator,
 }
@@ -86,7 +373,7 @@ impl Default for State {
         State {
             files: vec![],
             schedules: vec![],
-            status: Status::Init,
+            uploads: vec![],
             file_id_gen: IdGenerator::new(0),
             schedule_id_gen: IdGenerator::new(0),
         }
@@ -95,14 +382,39 @@ impl Default for State {
 
 impl State {
     pub fn load() -> State {
-        let files = load_media_files();
+        let mut files = load_media_files();
         let schedules = load_schedules();
+        // entries stored before metadata and hashes were kept get them now
+        let mut backfilled = false;
+        for file in files.iter_mut().filter(|f| f.kind.is_file()) {
+            if file.metadata.is_none() {
+                debug!("extracting metadata of: {}", file.path);
+                file.metadata = extract_metadata(Path::new(&file.path))
+                    .map_err(|e| warn!("error extracting metadata: {}", e))
+                    .ok();
+                backfilled = true;
+            }
+            if file.hash.is_none() {
+                debug!("hashing: {}", file.path);
+                file.hash = hash_file(Path::new(&file.path))
+                    .map_err(|e| warn!("error hashing {}: {}", file.path, e))
+                    .ok();
+                backfilled = true;
+            }
+        }
+        if backfilled {
+            write_media_files(&files);
+        }
+        State::new(files, schedules)
+    }
+
+    pub fn new(files: Vec<MediaFile>, schedules: Vec<Schedule>) -> State {
         State {
             file_id_gen: IdGenerator::new(files.iter().map(|f| f.id).max().unwrap_or(0)),
             schedule_id_gen: IdGenerator::new(schedules.iter().map(|s| s.id).max().unwrap_or(0)),
             files,
             schedules,
-            status: Status::Idle,
+            uploads: vec![],
         }
     }
 
@@ -114,14 +426,193 @@ impl State {
         write_schedules(&self.schedules);
     }
 
+    /// Writes everything out, used on shutdown.
+    pub fn flush(&self) {
+        self.save_media();
+        self.save_schedules();
+    }
+
     pub fn get_media(&self, id: u32) -> Option<&MediaFile> {
         self.files.iter().find(|f| f.id == id)
     }
 
-    pub fn add_media(&mut self, name: String, path: String) {
-        self.files
-            .push(MediaFile::new(self.file_id_gen.next(), name, path));
+    /// `file_id` wrapped in its pre- and post-roll, `None` if any of them is missing.
+    pub fn get_sequence(
+        &self,
+        file_id: u32,
+        pre_roll: Option<u32>,
+        post_roll: Option<u32>,
+    ) -> Option<Vec<MediaFile>> {
+        [pre_roll, Some(file_id), post_roll]
+            .iter()
+            .flatten()
+            .map(|id| self.get_media(*id).cloned())
+            .collect()
+    }
+
+    fn insert_media(
+        &mut self,
+        name: String,
+        path: String,
+        hash: String,
+        metadata: Option<MediaMetadata>,
+    ) -> &mut MediaFile {
+        // the library scan may have picked the file up before its creator got here
+        let existing = self
+            .files
+            .iter()
+            .position(|f| f.kind.is_file() && f.path == path);
+        let mut unique_name = name.clone();
+        let mut copy = 1;
+        while self
+            .files
+            .iter()
+            .enumerate()
+            .any(|(index, f)| Some(index) != existing && f.name == unique_name)
+        {
+            copy += 1;
+            unique_name = format!("{} ({})", name, copy);
+        }
+        let file = match existing {
+            Some(index) => {
+                let file = &mut self.files[index];
+                file.name = unique_name;
+                file.hash = Some(hash);
+                file
+            }
+            None => {
+                let file = MediaFile::new(self.file_id_gen.next(), unique_name, path, Some(hash));
+                self.files.push(file);
+                self.files.last_mut().unwrap()
+            }
+        };
+        file.metadata = metadata;
+        file
+    }
+
+    pub fn add_media(
+        &mut self,
+        name: String,
+        path: String,
+        hash: String,
+        metadata: Option<MediaMetadata>,
+    ) -> MediaFile {
+        let file = self.insert_media(name, path, hash, metadata).clone();
+        self.save_media();
+        file
+    }
+
+    /// Adds an edited version of an existing file, which is kept as is.
+    pub fn add_version(
+        &mut self,
+        source_id: u32,
+        name: String,
+        path: String,
+        hash: String,
+        metadata: Option<MediaMetadata>,
+    ) -> MediaFile {
+        let normalize = self.get_media(source_id).is_none_or(|f| f.normalize);
+        let file = self.insert_media(name, path, hash, metadata);
+        file.source_id = Some(source_id);
+        file.normalize = normalize;
+        let file = file.clone();
+        self.save_media();
+        file
+    }
+
+    pub fn add_generator(&mut self, name: String, generator: Generator) -> MediaFile {
+        let mut file = MediaFile::new(self.file_id_gen.next(), name, String::new(), None);
+        file.metadata = Some(MediaMetadata {
+            duration: Some(generator.duration() as f64),
+            sample_rate: Some(SAMPLE_RATE),
+            channels: Some(1),
+            ..Default::default()
+        });
+        file.kind = MediaKind::Generator(generator);
+        self.files.push(file.clone());
+        self.save_media();
+        file
+    }
+
+    pub fn add_stream(&mut self, name: String, url: String) -> MediaFile {
+        let mut file = MediaFile::new(self.file_id_gen.next(), name, String::new(), None);
+        file.kind = MediaKind::Stream { url };
+        self.files.push(file.clone());
         self.save_media();
+        file
+    }
+
+    /// Points an existing entry at new content, keeping its id so schedules stay intact.
+    /// Returns the previous path.
+    pub fn replace_media(
+        &mut self,
+        id: u32,
+        name: String,
+        path: String,
+        hash: String,
+        metadata: Option<MediaMetadata>,
+    ) -> String {
+        let file = self.files.iter_mut().find(|f| f.id == id).unwrap();
+        let old_path = std::mem::replace(&mut file.path, path);
+        file.name = name;
+        file.hash = Some(hash);
+        file.metadata = metadata;
+        file.kind = MediaKind::File;
+        file.missing = false;
+        self.save_media();
+        old_path
+    }
+
+    /// Existing file with the same content.
+    pub fn find_duplicate(&self, hash: &str) -> Option<&MediaFile> {
+        self.files.iter().find(|f| f.hash.as_deref() == Some(hash))
+    }
+
+    pub fn find_by_name(&self, name: &str) -> Option<&MediaFile> {
+        self.files.iter().find(|f| f.name == name)
+    }
+
+    pub fn set_normalize(&mut self, id: u32, normalize: bool) {
+        let file = self.files.iter_mut().find(|f| f.id == id).unwrap();
+        if file.normalize == normalize {
+            return;
+        }
+        file.normalize = normalize;
+        self.save_media();
+    }
+
+    pub fn set_loudness(&mut self, id: u32, loudness: Option<f64>) {
+        if let Some(file) = self.files.iter_mut().find(|f| f.id == id) {
+            file.metadata.get_or_insert_with(Default::default).loudness = loudness;
+            self.save_media();
+        }
+    }
+
+    /// Flags exactly the given files as missing and returns every missing file.
+    pub fn flag_missing(&mut self, missing: &[u32]) -> Vec<MediaFile> {
+        let mut changed = false;
+        for file in self.files.iter_mut() {
+            let is_missing = missing.contains(&file.id);
+            if file.missing == is_missing {
+                continue;
+            }
+            match is_missing {
+                true => warn!("media file {} is missing: {}", file.id, file.path),
+                false => info!("media file {} is back: {}", file.id, file.path),
+            }
+            file.missing = is_missing;
+            changed = true;
+        }
+        if changed {
+            self.save_media();
+        }
+        self.files.iter().filter(|f| f.missing).cloned().collect()
+    }
+
+    pub fn is_path_shared(&self, id: u32, path: &str) -> bool {
+        self.files
+            .iter()
+            .any(|f| f.id != id && f.kind.is_file() && f.path == path)
     }
 
     pub fn remove_media(&mut self, id: u32) {
@@ -137,22 +628,52 @@ impl State {
         self.schedules.iter_mut().find(|s| s.id == id)
     }
 
-    pub fn add_schedule(&mut self, file_id: u32, schedule: String) {
-        self.schedules.push(Schedule::new(
-            self.schedule_id_gen.next(),
-            file_id,
-            schedule,
-        ));
+    pub fn add_schedule(
+        &mut self,
+        file_id: u32,
+        schedule: String,
+        spec: Option<ScheduleSpec>,
+    ) -> u32 {
+        let id = self.schedule_id_gen.next();
+        self.schedules
+            .push(Schedule::new(id, file_id, schedule, spec));
         self.save_schedules();
+        id
     }
 
-    pub fn edit_schedule(&mut self, id: u32, file_id: u32, schedule: String) {
-        let mut sched = self.get_mut_schedule(id).unwrap();
-        if file_id == sched.file_id && schedule == sched.schedule {
+    pub fn edit_schedule(
+        &mut self,
+        id: u32,
+        file_id: u32,
+        schedule: String,
+        spec: Option<ScheduleSpec>,
+    ) {
+        let sched = self.get_mut_schedule(id).unwrap();
+        if file_id == sched.file_id && schedule == sched.schedule && spec == sched.spec {
             return;
         }
         sched.file_id = file_id;
         sched.schedule = schedule;
+        sched.spec = spec;
+        self.save_schedules();
+    }
+
+    pub fn set_rolls(&mut self, id: u32, pre_roll: Option<u32>, post_roll: Option<u32>) {
+        let sched = self.get_mut_schedule(id).unwrap();
+        if pre_roll == sched.pre_roll && post_roll == sched.post_roll {
+            return;
+        }
+        sched.pre_roll = pre_roll;
+        sched.post_roll = post_roll;
+        self.save_schedules();
+    }
+
+    pub fn set_zone(&mut self, id: u32, zone: Option<String>) {
+        let sched = self.get_mut_schedule(id).unwrap();
+        if zone == sched.zone {
+            return;
+        }
+        sched.zone = zone;
         self.save_schedules();
     }
 
@@ -160,10 +681,77 @@ impl State {
         self.schedules.retain(|s| s.id != id);
         self.save_schedules();
     }
+
+    /// Schedules playing the file, including as pre- or post-roll.
+    pub fn dependent_schedules(&self, file_id: u32) -> Vec<&Schedule> {
+        self.schedules.iter().filter(|s| s.uses(file_id)).collect()
+    }
+
+    /// Points every schedule using `from` at `to`.
+    pub fn reassign_file(&mut self, from: u32, to: u32) {
+        for schedule in self.schedules.iter_mut().filter(|s| s.file_id == from) {
+            schedule.file_id = to;
+        }
+        self.replace_rolls(from, Some(to));
+    }
+
+    /// Drops the file from the pre- and post-rolls it is used in.
+    pub fn clear_rolls(&mut self, file_id: u32) {
+        self.replace_rolls(file_id, None);
+    }
+
+    fn replace_rolls(&mut self, from: u32, to: Option<u32>) {
+        for schedule in self.schedules.iter_mut() {
+            for roll in [&mut schedule.pre_roll, &mut schedule.post_roll] {
+                if *roll == Some(from) {
+                    *roll = to;
+                }
+            }
+        }
+        self.save_schedules();
+    }
 }
 
 impl MediaFile {
-    pub fn new(id: u32, name: String, path: String) -> MediaFile {
-        MediaFile { id, name, path }
+    pub fn new(id: u32, name: String, path: String, hash: Option<String>) -> MediaFile {
+        MediaFile {
+            id,
+            name,
+            path,
+            hash,
+            metadata: None,
+            normalize: true,
+            source_id: None,
+            kind: MediaKind::File,
+            missing: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consts::{MEDIA_PATH, RESOURCE_PATH};
+    use crate::utils::test_store;
+
+    #[tokio::test]
+    async fn loading_backfills_old_entries() {
+        let _store = test_store().await;
+        let path = format!("{}/chime.mp3", MEDIA_PATH);
+        std::fs::copy("media/tripple_kill.mp3", &path).unwrap();
+        let resource = Path::new(RESOURCE_PATH);
+        let entry = format!(r#"[{{"id":0,"name":"chime","path":"{}"}}]"#, path);
+        std::fs::write(resource.join("media.json"), entry).unwrap();
+        std::fs::write(resource.join("schedules.json"), "[]").unwrap();
+
+        let state = State::load();
+        let file = state.get_media(0).unwrap();
+        let hash = hash_file(Path::new(&path)).unwrap();
+        assert_eq!(file.hash.as_ref(), Some(&hash));
+        assert!(file.metadata.as_ref().unwrap().duration.is_some());
+        // and stored, so it isn't done again on the next start
+        let stored = load_media_files();
+        assert_eq!(stored[0].hash.as_ref(), Some(&hash));
+        assert!(stored[0].metadata.is_some());
     }
 }
@@ -0,0 +1,458 @@
+use utoipa::OpenApi;
+
+use crate::handlers;
+
+/// OpenAPI document served at `/openapi.json`, browsable at `/docs/`.
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "rustyplayer",
+        description = "Plays and schedules audio on a Raspberry Pi. The unversioned routes \
+            (`/files`, `/play`, `/schedule`, ...) are deprecated, see the `legacy` tag for what \
+            replaces them under `/api/v1`."
+    ),
+    paths(
+        handlers::get_files,
+        handlers::create_files,
+        handlers::analyze_files,
+        handlers::scan_files,
+        handlers::get_file,
+        handlers::update_file,
+        handlers::remove_file_resource,
+        handlers::download_file,
+        handlers::create_trimmed,
+        handlers::create_version,
+        handlers::get_uploads,
+        handlers::create_generator,
+        handlers::create_stream,
+        handlers::create_announcement,
+        handlers::get_schedules,
+        handlers::create_schedule,
+        handlers::get_schedule,
+        handlers::replace_schedule,
+        handlers::update_schedule,
+        handlers::delete_schedule,
+        handlers::get_status,
+        handlers::get_zones,
+        handlers::get_devices,
+        handlers::start_playback,
+        handlers::playback_action,
+        handlers::health,
+        handlers::ready,
+        handlers::get_diagnostics,
+        handlers::get_metrics,
+        handlers::get_logs,
+        handlers::get_events,
+        legacy::status,
+        legacy::zones,
+        legacy::devices,
+        legacy::files,
+        legacy::uploads,
+        legacy::upload,
+        legacy::generator,
+        legacy::stream,
+        legacy::delete,
+        legacy::analyze,
+        legacy::normalize,
+        legacy::trim,
+        legacy::strip_silence,
+        legacy::transcode,
+        legacy::announce,
+        legacy::download,
+        legacy::stop,
+        legacy::pause,
+        legacy::resume,
+        legacy::play,
+        legacy::schedules,
+        legacy::schedule,
+        legacy::reschedule,
+        legacy::rolls,
+        legacy::zone,
+        legacy::remove,
+        legacy::activate,
+        legacy::deactivate,
+    ),
+    tags(
+        (name = "files", description = "Media files, generators, streams and announcements"),
+        (name = "schedules", description = "Cron schedules playing a file"),
+        (name = "playback", description = "Zones and what they play"),
+        (name = "monitoring", description = "Health, metrics and logs"),
+        (name = "legacy", description = "Deprecated routes kept until clients have moved to `/api/v1`"),
+    )
+)]
+pub struct ApiDoc;
+
+/// The routes superseded by `/api/v1`. Several share their handler with the
+/// route replacing them, so they are documented here instead of on the handlers.
+#[allow(dead_code)]
+mod legacy {
+    use crate::dto::ErrorResponse;
+    use crate::models::{
+        Announcement, DeleteOptions, MediaFile, Schedule, StatusReport, UploadOptions,
+        UploadProgress, ZoneOptions, ZoneStatus,
+    };
+
+    #[utoipa::path(
+        get,
+        path = "/status",
+        tag = "legacy",
+        summary = "Playback status, use `GET /api/v1/status`",
+        responses((status = 200, description = "Status of all zones", body = StatusReport))
+    )]
+    #[deprecated]
+    fn status() {}
+
+    #[utoipa::path(
+        get,
+        path = "/zones",
+        tag = "legacy",
+        summary = "Configured zones, use `GET /api/v1/zones`",
+        responses((status = 200, description = "Zones", body = [ZoneStatus]))
+    )]
+    #[deprecated]
+    fn zones() {}
+
+    #[utoipa::path(
+        get,
+        path = "/devices",
+        tag = "legacy",
+        summary = "Audio output devices, use `GET /api/v1/devices`",
+        responses((status = 200, description = "Device names", body = [String]))
+    )]
+    #[deprecated]
+    fn devices() {}
+
+    #[utoipa::path(
+        get,
+        path = "/files",
+        tag = "legacy",
+        summary = "All media files, use `GET /api/v1/files`",
+        responses((status = 200, description = "All media files", body = [MediaFile]))
+    )]
+    #[deprecated]
+    fn files() {}
+
+    #[utoipa::path(
+        get,
+        path = "/uploads",
+        tag = "legacy",
+        summary = "Uploads in progress, use `GET /api/v1/uploads`",
+        responses((status = 200, description = "Uploads in progress", body = [UploadProgress]))
+    )]
+    #[deprecated]
+    fn uploads() {}
+
+    #[utoipa::path(
+        post,
+        path = "/upload",
+        tag = "legacy",
+        summary = "Upload media files, use `POST /api/v1/files`",
+        params(UploadOptions),
+        request_body(content_type = "multipart/form-data", description = "One or more `file` parts"),
+        responses(
+            (status = 200, description = "Uploaded"),
+            (status = 400, description = "Not an audio file", body = ErrorResponse),
+            (status = 409, description = "The file already exists", body = ErrorResponse),
+        )
+    )]
+    #[deprecated]
+    fn upload() {}
+
+    #[utoipa::path(
+        post,
+        path = "/generator",
+        tag = "legacy",
+        summary = "Add a generated tone, use `POST /api/v1/generators`",
+        request_body(content_type = "application/json", description = "`[name, generator]`"),
+        responses(
+            (status = 200, description = "The generator", body = MediaFile),
+            (status = 400, description = "Invalid generator", body = ErrorResponse),
+        )
+    )]
+    #[deprecated]
+    fn generator() {}
+
+    #[utoipa::path(
+        post,
+        path = "/stream",
+        tag = "legacy",
+        summary = "Add an internet stream, use `POST /api/v1/streams`",
+        request_body(content_type = "application/json", description = "`[name, url]`"),
+        responses(
+            (status = 200, description = "The stream", body = MediaFile),
+            (status = 400, description = "Invalid url", body = ErrorResponse),
+        )
+    )]
+    #[deprecated]
+    fn stream() {}
+
+    #[utoipa::path(
+        get,
+        path = "/delete",
+        tag = "legacy",
+        summary = "Delete a file, use `DELETE /api/v1/files/{id}`",
+        params(("id" = u32, Query, description = "File id"), DeleteOptions),
+        responses(
+            (status = 200, description = "Deleted"),
+            (status = 404, description = "File not found", body = ErrorResponse),
+            (status = 409, description = "Schedules use the file", body = ErrorResponse),
+            (status = 500, description = "The file could not be deleted from disk", body = ErrorResponse),
+        )
+    )]
+    #[deprecated]
+    fn delete() {}
+
+    #[utoipa::path(
+        post,
+        path = "/analyze",
+        tag = "legacy",
+        summary = "Measure loudness, use `POST /api/v1/files/analyze`",
+        responses((status = 200, description = "Analysis started"))
+    )]
+    #[deprecated]
+    fn analyze() {}
+
+    #[utoipa::path(
+        post,
+        path = "/normalize",
+        tag = "legacy",
+        summary = "Toggle normalization, use `PATCH /api/v1/files/{id}`",
+        request_body(content_type = "application/json", description = "`[id, normalize]`"),
+        responses(
+            (status = 200, description = "Updated"),
+            (status = 404, description = "File not found", body = ErrorResponse),
+        )
+    )]
+    #[deprecated]
+    fn normalize() {}
+
+    #[utoipa::path(
+        post,
+        path = "/trim",
+        tag = "legacy",
+        summary = "Trim a file, use `POST /api/v1/files/{id}/trim`",
+        request_body(content_type = "application/json", description = "`[id, start, end]`, in seconds"),
+        responses(
+            (status = 200, description = "The trimmed copy", body = MediaFile),
+            (status = 400, description = "Invalid range", body = ErrorResponse),
+        )
+    )]
+    #[deprecated]
+    fn trim() {}
+
+    #[utoipa::path(
+        post,
+        path = "/strip_silence",
+        tag = "legacy",
+        summary = "Strip silence, use `POST /api/v1/files/{id}/strip-silence`",
+        params(("id" = u32, Query, description = "File id")),
+        responses(
+            (status = 200, description = "The stripped copy", body = MediaFile),
+            (status = 404, description = "File not found", body = ErrorResponse),
+        )
+    )]
+    #[deprecated]
+    fn strip_silence() {}
+
+    #[utoipa::path(
+        post,
+        path = "/transcode",
+        tag = "legacy",
+        summary = "Transcode to WAV, use `POST /api/v1/files/{id}/transcode`",
+        params(("id" = u32, Query, description = "File id")),
+        responses(
+            (status = 200, description = "The transcoded copy", body = MediaFile),
+            (status = 404, description = "File not found", body = ErrorResponse),
+        )
+    )]
+    #[deprecated]
+    fn transcode() {}
+
+    #[utoipa::path(
+        post,
+        path = "/announce",
+        tag = "legacy",
+        summary = "Synthesize an announcement, use `POST /api/v1/announcements`",
+        request_body = Announcement,
+        responses(
+            (status = 200, description = "The spoken announcement", body = MediaFile),
+            (status = 400, description = "Invalid request", body = ErrorResponse),
+        )
+    )]
+    #[deprecated]
+    fn announce() {}
+
+    #[utoipa::path(
+        get,
+        path = "/download",
+        tag = "legacy",
+        summary = "Download a file, use `GET /api/v1/files/{id}/content`",
+        params(("id" = u32, Query, description = "File id")),
+        responses(
+            (status = 301, description = "Redirect to the file"),
+            (status = 404, description = "File not found", body = ErrorResponse),
+        )
+    )]
+    #[deprecated]
+    fn download() {}
+
+    #[utoipa::path(
+        get,
+        path = "/stop",
+        tag = "legacy",
+        summary = "Stop playback, use `POST /api/v1/playback/stop`",
+        params(ZoneOptions),
+        responses((status = 200, description = "Stopped"))
+    )]
+    #[deprecated]
+    fn stop() {}
+
+    #[utoipa::path(
+        get,
+        path = "/pause",
+        tag = "legacy",
+        summary = "Pause playback, use `POST /api/v1/playback/pause`",
+        params(ZoneOptions),
+        responses((status = 200, description = "Paused"))
+    )]
+    #[deprecated]
+    fn pause() {}
+
+    #[utoipa::path(
+        get,
+        path = "/resume",
+        tag = "legacy",
+        summary = "Resume playback, use `POST /api/v1/playback/resume`",
+        params(ZoneOptions),
+        responses((status = 200, description = "Resumed"))
+    )]
+    #[deprecated]
+    fn resume() {}
+
+    #[utoipa::path(
+        get,
+        path = "/play",
+        tag = "legacy",
+        summary = "Play a file, use `POST /api/v1/playback`",
+        params(
+            ("id" = u32, Query, description = "File id"),
+            ("pre_roll" = Option<u32>, Query, description = "File played before"),
+            ("post_roll" = Option<u32>, Query, description = "File played after"),
+            ("zone" = Option<String>, Query, description = "Zone to play in, the default zone if not set"),
+        ),
+        responses(
+            (status = 200, description = "Queued for playback"),
+            (status = 404, description = "File not found", body = ErrorResponse),
+        )
+    )]
+    #[deprecated]
+    fn play() {}
+
+    #[utoipa::path(
+        get,
+        path = "/schedules",
+        tag = "legacy",
+        summary = "All schedules, use `GET /api/v1/schedules`",
+        responses((status = 200, description = "All schedules", body = [Schedule]))
+    )]
+    #[deprecated]
+    fn schedules() {}
+
+    #[utoipa::path(
+        post,
+        path = "/schedule",
+        tag = "legacy",
+        summary = "Add a schedule, use `POST /api/v1/schedules`",
+        request_body(content_type = "application/json", description = "`[file_id, schedule]`, optionally followed by the zone"),
+        responses(
+            (status = 200, description = "Added"),
+            (status = 400, description = "Invalid schedule", body = ErrorResponse),
+        )
+    )]
+    #[deprecated]
+    fn schedule() {}
+
+    #[utoipa::path(
+        post,
+        path = "/reschedule",
+        tag = "legacy",
+        summary = "Edit a schedule, use `PUT /api/v1/schedules/{id}`",
+        request_body(content_type = "application/json", description = "`[id, file_id, schedule]`, optionally followed by the zone"),
+        responses(
+            (status = 200, description = "Updated"),
+            (status = 404, description = "Schedule not found", body = ErrorResponse),
+        )
+    )]
+    #[deprecated]
+    fn reschedule() {}
+
+    #[utoipa::path(
+        post,
+        path = "/rolls",
+        tag = "legacy",
+        summary = "Set pre- and post-rolls, use `PATCH /api/v1/schedules/{id}`",
+        request_body(content_type = "application/json", description = "`[id, pre_roll, post_roll]`"),
+        responses(
+            (status = 200, description = "Updated"),
+            (status = 404, description = "Schedule not found", body = ErrorResponse),
+        )
+    )]
+    #[deprecated]
+    fn rolls() {}
+
+    #[utoipa::path(
+        post,
+        path = "/zone",
+        tag = "legacy",
+        summary = "Set the zone of a schedule, use `PATCH /api/v1/schedules/{id}`",
+        request_body(content_type = "application/json", description = "`[id, zone]`"),
+        responses(
+            (status = 200, description = "Updated"),
+            (status = 404, description = "Schedule not found", body = ErrorResponse),
+        )
+    )]
+    #[deprecated]
+    fn zone() {}
+
+    #[utoipa::path(
+        get,
+        path = "/remove",
+        tag = "legacy",
+        summary = "Delete a schedule, use `DELETE /api/v1/schedules/{id}`",
+        params(("id" = u32, Query, description = "Schedule id")),
+        responses(
+            (status = 200, description = "Deleted"),
+            (status = 404, description = "Schedule not found", body = ErrorResponse),
+        )
+    )]
+    #[deprecated]
+    fn remove() {}
+
+    #[utoipa::path(
+        get,
+        path = "/activate",
+        tag = "legacy",
+        summary = "Activate a schedule, use `PATCH /api/v1/schedules/{id}`",
+        params(("id" = u32, Query, description = "Schedule id")),
+        responses(
+            (status = 200, description = "Activated"),
+            (status = 404, description = "Schedule not found", body = ErrorResponse),
+        )
+    )]
+    #[deprecated]
+    fn activate() {}
+
+    #[utoipa::path(
+        get,
+        path = "/deactivate",
+        tag = "legacy",
+        summary = "Deactivate a schedule, use `PATCH /api/v1/schedules/{id}`",
+        params(("id" = u32, Query, description = "Schedule id")),
+        responses(
+            (status = 200, description = "Deactivated"),
+            (status = 404, description = "Schedule not found", body = ErrorResponse),
+        )
+    )]
+    #[deprecated]
+    fn deactivate() {}
+}
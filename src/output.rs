@@ -0,0 +1,87 @@
+//! Human readable output of the command line, shared by `client` and `admin`.
+
+use crate::models::{Activity, LogEntry, MediaFile, MediaKind, Schedule, StatusReport};
+
+pub fn print_status(status: &StatusReport) {
+    for zone in &status.zones {
+        let device = zone.device.as_deref().unwrap_or("no device");
+        print!(
+            "{:<12} {:<12} {}",
+            zone.zone,
+            format!("{:?}", zone.status),
+            device
+        );
+        if let Some(stream) = &zone.stream {
+            print!("  {}", stream.title.as_deref().unwrap_or(&stream.url));
+        }
+        println!();
+    }
+}
+
+pub fn print_file(file: &MediaFile) {
+    let duration = file
+        .metadata
+        .as_ref()
+        .and_then(|metadata| metadata.duration)
+        .map(|duration| {
+            let seconds = duration.round() as u64;
+            format!("{}:{:02}", seconds / 60, seconds % 60)
+        })
+        .unwrap_or_else(|| "-".to_string());
+    let kind = match &file.kind {
+        MediaKind::File => "file",
+        MediaKind::Generator(_) => "generator",
+        MediaKind::Stream { .. } => "stream",
+    };
+    println!("{:>5}  {:<9} {:>7}  {}", file.id, kind, duration, file.name);
+}
+
+pub fn print_schedule(schedule: &Schedule) {
+    let active = match schedule.activity {
+        Activity::Active => "active",
+        Activity::Inactive => "inactive",
+    };
+    let mut line = format!(
+        "{:>5}  {:<8}  file {:<5} {}",
+        schedule.id, active, schedule.file_id, schedule.schedule
+    );
+    if let Some(pre_roll) = schedule.pre_roll {
+        line.push_str(&format!("  pre-roll {}", pre_roll));
+    }
+    if let Some(post_roll) = schedule.post_roll {
+        line.push_str(&format!("  post-roll {}", post_roll));
+    }
+    if let Some(zone) = &schedule.zone {
+        line.push_str(&format!("  zone {}", zone));
+    }
+    println!("{}", line);
+}
+
+/// Prints an event the way the server logs it, spans first.
+pub fn print_event(entry: &LogEntry) {
+    let mut line = format!("{} {:>5} ", entry.time, entry.level);
+    for span in &entry.spans {
+        line.push_str(&format!(
+            "{}{{{}}}: ",
+            span.name,
+            fields(&span.fields).trim()
+        ));
+    }
+    line.push_str(&format!(
+        "{}: {}{}",
+        entry.target,
+        entry.message,
+        fields(&entry.fields)
+    ));
+    println!("{}", line);
+}
+
+fn fields(fields: &serde_json::Map<String, serde_json::Value>) -> String {
+    fields
+        .iter()
+        .map(|(key, value)| match value {
+            serde_json::Value::String(value) => format!(" {}={}", key, value),
+            value => format!(" {}={}", key, value),
+        })
+        .collect()
+}
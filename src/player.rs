@@ -1,51 +1,192 @@
 use rodio::Decoder;
 use rodio::OutputStreamHandle;
 use rodio::Sink;
+use rodio::Source;
 use std::fs::File;
 use std::path::Path;
+use std::sync::{Arc, Mutex};
+use tracing::info;
 
-use crate::models::MediaFile;
+use crate::loudness::normalization_gain;
+use crate::metrics::Metered;
+use crate::models::{MediaFile, MediaKind, Status, StreamStatus, ZoneStatus};
+use crate::stream::{HttpStream, StreamInfo};
+use crate::zones::ZoneConfig;
+
+type BoxedSource = Box<dyn Source<Item = i16> + Send>;
 
 pub struct Player {
-    sink: Sink,
+    zone: ZoneConfig,
+    /// `None` while the output device of the zone is unavailable.
+    sink: Mutex<Option<Sink>>,
+    target_loudness: f64,
+    stream: Mutex<Option<StreamInfo>>,
 }
 
 impl Player {
-    pub fn new(stream_handle: &OutputStreamHandle) -> Player {
+    /// Creates a disconnected player, see `connect`.
+    pub fn new(zone: ZoneConfig, target_loudness: f64) -> Player {
         Player {
-            sink: Sink::try_new(stream_handle).unwrap(),
+            zone,
+            sink: Mutex::new(None),
+            target_loudness,
+            stream: Mutex::new(None),
+        }
+    }
+
+    pub fn zone(&self) -> &ZoneConfig {
+        &self.zone
+    }
+
+    /// Starts playing through a (re)opened output device.
+    pub fn connect(&self, stream_handle: &OutputStreamHandle) -> Result<(), String> {
+        let sink = Sink::try_new(stream_handle).map_err(|e| e.to_string())?;
+        *self.sink.lock().unwrap() = Some(sink);
+        Ok(())
+    }
+
+    /// Drops everything queued, the device is gone.
+    pub fn disconnect(&self) {
+        if let Some(sink) = self.sink.lock().unwrap().take() {
+            sink.stop();
         }
+        *self.stream.lock().unwrap() = None;
     }
 
-    pub fn play(&self, mediafile: &MediaFile) {
-        let file = mediafile.path.as_str();
-        println!("Playing: {}", file);
-        let file_path = Path::new(file);
-        let file = File::open(file_path).unwrap();
-        let source = Decoder::new(file).unwrap();
-        self.sink.append(source);
-        self.sink.play();
+    fn gain(&self, mediafile: &MediaFile) -> f32 {
+        let loudness = mediafile.metadata.as_ref().and_then(|m| m.loudness);
+        match loudness {
+            Some(loudness) if mediafile.normalize => {
+                normalization_gain(loudness, self.target_loudness)
+            }
+            _ => 1.0,
+        }
+    }
+
+    fn source(&self, mediafile: &MediaFile) -> Result<BoxedSource, String> {
+        let source: BoxedSource = match &mediafile.kind {
+            MediaKind::File => {
+                let file = mediafile.path.as_str();
+                info!("Playing: {}", file);
+                let file_path = Path::new(file);
+                let file = File::open(file_path)
+                    .map_err(|e| format!("error opening {}: {}", mediafile.path, e))?;
+                let source = Decoder::new(file)
+                    .map_err(|e| format!("error decoding {}: {}", mediafile.path, e))?;
+                Box::new(source.amplify(self.gain(mediafile)))
+            }
+            MediaKind::Generator(generator) => {
+                info!("Playing generator: {}", mediafile.name);
+                Box::new(generator.source().convert_samples())
+            }
+            MediaKind::Stream { url } => {
+                info!("Playing stream: {}", url);
+                let info = StreamInfo {
+                    url: url.clone(),
+                    title: Arc::new(Mutex::new(None)),
+                };
+                let source = HttpStream::open(&info);
+                *self.stream.lock().unwrap() = Some(info);
+                Box::new(source)
+            }
+        };
+        Ok(source)
+    }
+
+    pub fn play(&self, mediafile: &MediaFile) -> Result<(), String> {
+        self.play_sequence(std::slice::from_ref(mediafile))
+    }
+
+    /// Plays the files back to back as a single entry in the queue,
+    /// so e.g. a chime, message and chime go out without gaps.
+    pub fn play_sequence(&self, mediafiles: &[MediaFile]) -> Result<(), String> {
+        let sink = self.sink.lock().unwrap();
+        let Some(sink) = sink.as_ref() else {
+            return Err(format!("zone {} is disconnected", self.zone.name));
+        };
+        let sources = mediafiles
+            .iter()
+            .map(|mediafile| self.source(mediafile))
+            .collect::<Result<Vec<BoxedSource>, String>>()?;
+        let sequence = rodio::source::from_iter(sources);
+        sink.append(Metered::new(sequence, &self.zone.name));
+        sink.play();
+        Ok(())
     }
 
     pub fn pause(&self) {
-        self.sink.pause();
+        if let Some(sink) = self.sink.lock().unwrap().as_ref() {
+            sink.pause();
+        }
     }
 
     pub fn resume(&self) {
-        self.sink.play();
+        if let Some(sink) = self.sink.lock().unwrap().as_ref() {
+            sink.play();
+        }
+    }
+
+    pub fn set_volume(&self, volume: f32) {
+        if let Some(sink) = self.sink.lock().unwrap().as_ref() {
+            sink.set_volume(volume);
+        }
     }
 
     pub fn stop(&self) {
-        self.sink.stop();
+        if let Some(sink) = self.sink.lock().unwrap().as_ref() {
+            sink.stop();
+        }
+        *self.stream.lock().unwrap() = None;
+    }
+
+    /// Last stream started, with the title its server announced.
+    pub fn stream_status(&self) -> Option<StreamStatus> {
+        self.stream
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|info| StreamStatus {
+                url: info.url.clone(),
+                title: info.title.lock().unwrap().clone(),
+            })
+    }
+
+    pub fn status(&self) -> Status {
+        match self.sink.lock().unwrap().as_ref() {
+            None => Status::Disconnected,
+            Some(sink) if sink.empty() => Status::Idle,
+            Some(sink) if sink.is_paused() => Status::Paused,
+            Some(_) => Status::Running,
+        }
+    }
+
+    pub fn zone_status(&self) -> ZoneStatus {
+        let status = self.status();
+        let stream = match status {
+            Status::Running | Status::Paused => self.stream_status(),
+            _ => None,
+        };
+        ZoneStatus {
+            zone: self.zone.name.clone(),
+            device: self.zone.device.clone(),
+            status,
+            stream,
+        }
+    }
+
+    pub fn queue_len(&self) -> usize {
+        self.sink.lock().unwrap().as_ref().map_or(0, Sink::len)
     }
 
     pub fn done(&self) -> bool {
-        self.sink.empty()
+        self.sink.lock().unwrap().as_ref().is_none_or(Sink::empty)
     }
 
     pub fn skip(&self, count: Option<u32>) {
-        for _ in 0..count.unwrap_or(1) {
-            self.sink.skip_one();
+        if let Some(sink) = self.sink.lock().unwrap().as_ref() {
+            for _ in 0..count.unwrap_or(1) {
+                sink.skip_one();
+            }
         }
     }
 }
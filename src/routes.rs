@@ -1,80 +1,306 @@
 use hyper::StatusCode;
 use std::collections::HashMap;
 use std::convert::Infallible;
+use tracing::{debug, error, info};
+use warp::filters::BoxedFilter;
 use warp::multipart::form;
-use warp::{any, body, get, path, post, Filter, Rejection, Reply};
+use warp::{any, body, get, header, path, post, Filter, Rejection, Reply};
 
+use crate::api;
 use crate::consts::MEDIA_PATH;
-use crate::consts::WEB_PATH;
+use crate::generator::Generator;
 use crate::handlers;
-use crate::PlayerMutex;
+use crate::handlers::{
+    DeletionFailed, DuplicateFile, FileInUse, FileNotFound, FileTooLarge, InvalidAnnouncement,
+    InvalidDeletion, InvalidEdit, InvalidFile, InvalidGenerator, InvalidLogQuery, InvalidSchedule,
+    InvalidStream, MediaMissing, ScanFailed, ScheduleNotFound, SpeechFailed, UnknownZone,
+    ZoneUnavailable,
+};
+use crate::models::{
+    Announcement, DeleteOptions, EditedSchedule, ExportOptions, LogQuery, NewSchedule, PlayOptions,
+    UploadOptions, ZoneOptions,
+};
+use crate::utils::{max_upload_size, percent_encode, web_dir};
 use crate::SchedulerMutex;
 use crate::StateMutex;
+use crate::ZonesMutex;
 
 pub fn routes(
     state: StateMutex,
-    player: PlayerMutex,
+    zones: ZonesMutex,
     scheduler: SchedulerMutex,
-) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
-    serve_web()
-        .or(serve_files())
-        .or(get_status(state.clone(), player.clone()))
-        .or(get_schedules(state.clone()))
-        .or(get_files(state.clone()))
+) -> impl Filter<Extract = (impl Reply,), Error = Infallible> + Clone {
+    let api = api::routes(state.clone(), zones.clone(), scheduler.clone());
+    // grouped and boxed, a single chain this long overflows the compiler's type depth
+    let monitoring = health()
+        .or(ready(zones.clone(), scheduler.clone()))
+        .or(get_diagnostics(zones.clone(), scheduler.clone()))
+        .or(get_metrics(zones.clone(), scheduler.clone()))
+        .or(get_logs())
+        .or(get_openapi())
+        .boxed();
+    // superseded by /api/v1, kept until clients have moved over
+    let deprecated = warp::reply::with::header("deprecation", "true");
+    let status = get_status(zones.clone())
+        .or(get_zones(zones.clone()))
+        .or(get_devices())
+        .with(deprecated.clone())
+        .boxed();
+    let media = get_files(state.clone())
+        .or(get_uploads(state.clone()))
         .or(upload_files(state.clone()))
+        .or(add_generator(state.clone()))
+        .or(add_stream(state.clone()))
         .or(delete_file(state.clone(), scheduler.clone()))
+        .or(analyze(state.clone()))
+        .or(set_normalize(state.clone()))
+        .or(trim_file(state.clone()))
+        .or(strip_silence(state.clone()))
+        .or(transcode(state.clone()))
+        .or(announce(state.clone(), zones.clone(), scheduler.clone()))
         .or(download_file(state.clone()))
-        .or(stop(state.clone(), player.clone()))
-        .or(play(state.clone(), player.clone()))
-        .or(pause(state.clone(), player.clone()))
-        .or(resume(state.clone(), player))
-        .or(add_schedule(state.clone()))
-        .or(edit_schedule(state.clone(), scheduler.clone()))
-        .or(remove_schedule(state, scheduler.clone()))
-        .or(activate(scheduler.clone()))
-        .or(deactivate(scheduler))
-}
-
-fn serve_web() -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
-    warp::path::end()
-        .and(get())
-        .and(warp::fs::dir(WEB_PATH).recover(handle_rejection))
+        .with(deprecated.clone())
+        .boxed();
+    let playback = stop(zones.clone())
+        .or(play(state.clone(), zones.clone()))
+        .or(pause(zones.clone()))
+        .or(resume(zones.clone()))
+        .with(deprecated.clone())
+        .boxed();
+    let schedules = get_schedules(state.clone())
+        .or(add_schedule(state.clone(), zones.clone()))
+        .or(edit_schedule(
+            state.clone(),
+            zones.clone(),
+            scheduler.clone(),
+        ))
+        .or(set_rolls(state.clone()))
+        .or(set_zone(state.clone(), zones))
+        .or(remove_schedule(state.clone(), scheduler.clone()))
+        .or(activate(state.clone(), scheduler.clone()))
+        .or(deactivate(state, scheduler))
+        .with(deprecated)
+        .boxed();
+    serve_web()
+        .or(serve_files())
+        .or(api)
+        .or(monitoring)
+        .or(status)
+        .or(media)
+        .or(playback)
+        .or(schedules)
+        .recover(handle_rejection)
+}
+
+/// The web UI, embedded into the binary unless `WEB_DIR` points at a copy to serve instead.
+fn serve_web() -> BoxedFilter<(Box<dyn Reply>,)> {
+    match web_dir() {
+        Some(dir) => {
+            info!("Serving the web UI from: {}", dir);
+            get()
+                .and(warp::fs::dir(dir))
+                .map(|file| Box::new(file) as Box<dyn Reply>)
+                .boxed()
+        }
+        None => get()
+            .and(path::tail())
+            .and(header::optional::<String>("accept-encoding"))
+            .and(header::optional::<String>("if-none-match"))
+            .and_then(handlers::serve_asset)
+            .map(|response| Box::new(response) as Box<dyn Reply>)
+            .boxed(),
+    }
 }
 
 fn serve_files() -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
-    path("export")
-        .and(get())
-        .and(warp::fs::dir(MEDIA_PATH).recover(handle_rejection))
+    path("export").and(get()).and(
+        warp::query::<ExportOptions>()
+            .and(warp::fs::dir(MEDIA_PATH))
+            .map(named_download)
+            .recover(handle_rejection),
+    )
+}
+
+/// Adds the name passed along by `/download`, files are stored under generated ones.
+fn named_download(options: ExportOptions, file: warp::fs::File) -> Box<dyn Reply> {
+    let Some(name) = options.name else {
+        return Box::new(file);
+    };
+    // for clients that don't understand `filename*`
+    let fallback = name.replace(
+        |c: char| !c.is_ascii() || c.is_ascii_control() || c == '"',
+        "_",
+    );
+    Box::new(warp::reply::with_header(
+        file,
+        "content-disposition",
+        format!(
+            "attachment; filename=\"{}\"; filename*=UTF-8''{}",
+            fallback,
+            percent_encode(&name)
+        ),
+    ))
 }
 
 async fn handle_rejection(err: Rejection) -> std::result::Result<impl Reply, Infallible> {
-    println!("Rejection: {:?}", err);
-    let (code, message) = if err.is_not_found() {
+    let (code, message) = describe_rejection(&err);
+    Ok(warp::reply::with_status(message, code))
+}
+
+/// Status code and message a rejection is answered with.
+pub fn describe_rejection(err: &Rejection) -> (StatusCode, String) {
+    debug!("Rejection: {:?}", err);
+    if err.is_not_found() {
         (StatusCode::NOT_FOUND, "Not Found".to_string())
     } else if err.find::<warp::reject::PayloadTooLarge>().is_some() {
-        (StatusCode::BAD_REQUEST, "Payload too large".to_string())
+        (
+            StatusCode::PAYLOAD_TOO_LARGE,
+            "Payload too large".to_string(),
+        )
+    } else if let Some(FileTooLarge(limit)) = err.find::<FileTooLarge>() {
+        (
+            StatusCode::PAYLOAD_TOO_LARGE,
+            format!("File exceeds the upload limit of {} bytes", limit),
+        )
+    } else if err.find::<InvalidFile>().is_some() {
+        (StatusCode::BAD_REQUEST, "Invalid file".to_string())
+    } else if let Some(DuplicateFile(id)) = err.find::<DuplicateFile>() {
+        (
+            StatusCode::CONFLICT,
+            format!("File already exists with id {}", id),
+        )
+    } else if let Some(InvalidEdit(message)) = err.find::<InvalidEdit>() {
+        (StatusCode::BAD_REQUEST, message.clone())
+    } else if let Some(InvalidAnnouncement(message)) = err.find::<InvalidAnnouncement>() {
+        (StatusCode::BAD_REQUEST, message.clone())
+    } else if let Some(SpeechFailed(message)) = err.find::<SpeechFailed>() {
+        (StatusCode::INTERNAL_SERVER_ERROR, message.clone())
+    } else if let Some(InvalidGenerator(message)) = err.find::<InvalidGenerator>() {
+        (StatusCode::BAD_REQUEST, message.clone())
+    } else if let Some(InvalidStream(message)) = err.find::<InvalidStream>() {
+        (StatusCode::BAD_REQUEST, message.clone())
+    } else if let Some(InvalidLogQuery(message)) = err.find::<InvalidLogQuery>() {
+        (StatusCode::BAD_REQUEST, message.clone())
+    } else if let Some(UnknownZone(zone)) = err.find::<UnknownZone>() {
+        (StatusCode::BAD_REQUEST, format!("Unknown zone: {}", zone))
+    } else if let Some(ZoneUnavailable(message)) = err.find::<ZoneUnavailable>() {
+        (StatusCode::SERVICE_UNAVAILABLE, message.clone())
+    } else if let Some(FileNotFound(id)) = err.find::<FileNotFound>() {
+        (StatusCode::NOT_FOUND, format!("File {} not found", id))
+    } else if let Some(ScheduleNotFound(id)) = err.find::<ScheduleNotFound>() {
+        (StatusCode::NOT_FOUND, format!("Schedule {} not found", id))
+    } else if let Some(FileInUse(id, schedules)) = err.find::<FileInUse>() {
+        let schedules = schedules
+            .iter()
+            .map(|id| id.to_string())
+            .collect::<Vec<String>>();
+        (
+            StatusCode::CONFLICT,
+            format!(
+                "File {} is used by schedules {}, delete it with force, cascade or reassign",
+                id,
+                schedules.join(", ")
+            ),
+        )
+    } else if let Some(InvalidDeletion(message)) = err.find::<InvalidDeletion>() {
+        (StatusCode::BAD_REQUEST, message.clone())
+    } else if let Some(DeletionFailed(message)) = err.find::<DeletionFailed>() {
+        (StatusCode::INTERNAL_SERVER_ERROR, message.clone())
+    } else if let Some(MediaMissing(id)) = err.find::<MediaMissing>() {
+        (
+            StatusCode::CONFLICT,
+            format!("File {} is missing from the media directory", id),
+        )
+    } else if let Some(ScanFailed(message)) = err.find::<ScanFailed>() {
+        (StatusCode::INTERNAL_SERVER_ERROR, message.clone())
+    } else if let Some(InvalidSchedule(message)) = err.find::<InvalidSchedule>() {
+        (StatusCode::BAD_REQUEST, message.clone())
+    } else if let Some(e) = err.find::<warp::filters::body::BodyDeserializeError>() {
+        (StatusCode::BAD_REQUEST, e.to_string())
+    } else if err.find::<warp::reject::MethodNotAllowed>().is_some() {
+        (
+            StatusCode::METHOD_NOT_ALLOWED,
+            "Method Not Allowed".to_string(),
+        )
     } else {
-        eprintln!("unhandled error: {:?}", err);
+        error!("unhandled error: {:?}", err);
         (
             StatusCode::INTERNAL_SERVER_ERROR,
             "Internal Server Error".to_string(),
         )
-    };
-
-    Ok(warp::reply::with_status(message, code))
+    }
 }
 
 fn get_status(
-    state: StateMutex,
-    player: PlayerMutex,
+    zones: ZonesMutex,
 ) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
     path("status")
         .and(get())
-        .and(with_state(state))
-        .and(with_stream(player))
+        .and(with_zones(zones))
         .and_then(handlers::get_status)
 }
 
+fn health() -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    path("health").and(get()).and_then(handlers::health)
+}
+
+fn ready(
+    zones: ZonesMutex,
+    scheduler: SchedulerMutex,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    path("ready")
+        .and(get())
+        .and(with_zones(zones))
+        .and(with_scheduler(scheduler))
+        .and_then(handlers::ready)
+}
+
+fn get_diagnostics(
+    zones: ZonesMutex,
+    scheduler: SchedulerMutex,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    path("diagnostics")
+        .and(get())
+        .and(with_zones(zones))
+        .and(with_scheduler(scheduler))
+        .and_then(handlers::get_diagnostics)
+}
+
+fn get_metrics(
+    zones: ZonesMutex,
+    scheduler: SchedulerMutex,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    path("metrics")
+        .and(get())
+        .and(with_zones(zones))
+        .and(with_scheduler(scheduler))
+        .and_then(handlers::get_metrics)
+}
+
+fn get_logs() -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    path("logs")
+        .and(get())
+        .and(warp::query::<LogQuery>())
+        .and_then(handlers::get_logs)
+}
+
+fn get_openapi() -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    path("openapi.json")
+        .and(get())
+        .and_then(handlers::get_openapi)
+}
+
+fn get_zones(zones: ZonesMutex) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    path("zones")
+        .and(get())
+        .and(with_zones(zones))
+        .and_then(handlers::get_zones)
+}
+
+fn get_devices() -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    path("devices").and(get()).and_then(handlers::get_devices)
+}
+
 fn get_files(state: StateMutex) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
     path("files")
         .and(get())
@@ -91,16 +317,103 @@ fn get_schedules(
         .and_then(handlers::get_schedules)
 }
 
+fn get_uploads(
+    state: StateMutex,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    path("uploads")
+        .and(get())
+        .and(with_state(state))
+        .and_then(handlers::get_uploads)
+}
+
 fn upload_files(
     state: StateMutex,
 ) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
     path("upload")
         .and(post())
-        .and(form())
+        .and(warp::query::<UploadOptions>())
+        .and(form().max_length(max_upload_size()))
         .and(with_state(state))
         .and_then(handlers::upload_files)
 }
 
+fn analyze(state: StateMutex) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    path("analyze")
+        .and(post())
+        .and(with_state(state))
+        .and_then(handlers::analyze)
+}
+
+fn set_normalize(
+    state: StateMutex,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    path("normalize")
+        .and(post())
+        .and(json_normalize())
+        .and(with_state(state))
+        .and_then(handlers::set_normalize)
+}
+
+fn trim_file(state: StateMutex) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    path("trim")
+        .and(post())
+        .and(json_trim())
+        .and(with_state(state))
+        .and_then(handlers::trim_file)
+}
+
+fn strip_silence(
+    state: StateMutex,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    path("strip_silence")
+        .and(post())
+        .and(with_id())
+        .and(with_state(state))
+        .and_then(handlers::strip_silence)
+}
+
+fn transcode(state: StateMutex) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    path("transcode")
+        .and(post())
+        .and(with_id())
+        .and(with_state(state))
+        .and_then(handlers::transcode)
+}
+
+fn announce(
+    state: StateMutex,
+    zones: ZonesMutex,
+    scheduler: SchedulerMutex,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    path("announce")
+        .and(post())
+        .and(json_announcement())
+        .and(with_state(state))
+        .and(with_zones(zones))
+        .and(with_scheduler(scheduler))
+        .and_then(handlers::announce)
+}
+
+fn add_generator(
+    state: StateMutex,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    path("generator")
+        .and(post())
+        .and(json_generator())
+        .and(with_state(state))
+        .and_then(handlers::add_generator)
+}
+
+fn add_stream(
+    state: StateMutex,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    path("stream")
+        .and(post())
+        .and(json_stream())
+        .and(with_state(state))
+        .and_then(handlers::add_stream)
+}
+
 fn delete_file(
     state: StateMutex,
     scheduler: SchedulerMutex,
@@ -108,6 +421,7 @@ fn delete_file(
     path!("delete")
         .and(get())
         .and(with_id())
+        .and(warp::query::<DeleteOptions>())
         .and(with_state(state))
         .and(with_scheduler(scheduler))
         .and_then(handlers::delete_file)
@@ -123,73 +437,89 @@ fn download_file(
         .and_then(handlers::download_file)
 }
 
-fn pause(
-    state: StateMutex,
-    player: PlayerMutex,
-) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+fn pause(zones: ZonesMutex) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
     path("pause")
         .and(get())
-        .and(with_state(state))
-        .and(with_stream(player))
+        .and(warp::query::<ZoneOptions>())
+        .and(with_zones(zones))
         .and_then(handlers::pause)
 }
 
-fn resume(
-    state: StateMutex,
-    player: PlayerMutex,
-) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+fn resume(zones: ZonesMutex) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
     path("resume")
         .and(get())
-        .and(with_state(state))
-        .and(with_stream(player))
+        .and(warp::query::<ZoneOptions>())
+        .and(with_zones(zones))
         .and_then(handlers::resume)
 }
 
-fn stop(
-    state: StateMutex,
-    player: PlayerMutex,
-) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+fn stop(zones: ZonesMutex) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
     path("stop")
         .and(get())
-        .and(with_state(state))
-        .and(with_stream(player))
+        .and(warp::query::<ZoneOptions>())
+        .and(with_zones(zones))
         .and_then(handlers::stop)
 }
 
 fn play(
     state: StateMutex,
-    player: PlayerMutex,
+    zones: ZonesMutex,
 ) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
     path("play")
         .and(get())
         .and(with_id())
+        .and(warp::query::<PlayOptions>())
         .and(with_state(state))
-        .and(with_stream(player))
+        .and(with_zones(zones))
         .and_then(handlers::play)
 }
 
 fn edit_schedule(
     state: StateMutex,
+    zones: ZonesMutex,
     scheduler: SchedulerMutex,
 ) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
     path("reschedule")
         .and(post())
         .and(json_edit_schedule())
         .and(with_state(state))
+        .and(with_zones(zones))
         .and(with_scheduler(scheduler))
         .and_then(handlers::edit_schedule)
 }
 
 fn add_schedule(
     state: StateMutex,
+    zones: ZonesMutex,
 ) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
     path("schedule")
         .and(post())
         .and(json_schedule())
         .and(with_state(state))
+        .and(with_zones(zones))
         .and_then(handlers::add_schedule)
 }
 
+fn set_rolls(state: StateMutex) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    path("rolls")
+        .and(post())
+        .and(json_rolls())
+        .and(with_state(state))
+        .and_then(handlers::set_rolls)
+}
+
+fn set_zone(
+    state: StateMutex,
+    zones: ZonesMutex,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    path("zone")
+        .and(post())
+        .and(json_zone())
+        .and(with_state(state))
+        .and(with_zones(zones))
+        .and_then(handlers::set_zone)
+}
+
 fn remove_schedule(
     state: StateMutex,
     scheduler: SchedulerMutex,
@@ -203,41 +533,45 @@ fn remove_schedule(
 }
 
 fn activate(
+    state: StateMutex,
     scheduler: SchedulerMutex,
 ) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
     path("activate")
         .and(get())
         .and(with_id())
+        .and(with_state(state))
         .and(with_scheduler(scheduler))
         .and_then(handlers::activate)
 }
 
 fn deactivate(
+    state: StateMutex,
     scheduler: SchedulerMutex,
 ) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
     path("deactivate")
         .and(get())
         .and(with_id())
+        .and(with_state(state))
         .and(with_scheduler(scheduler))
         .and_then(handlers::deactivate)
 }
 
-fn with_state(
+pub fn with_state(
     state: StateMutex,
 ) -> impl Filter<Extract = (StateMutex,), Error = Infallible> + Clone {
     any().map(move || state.clone())
 }
 
-fn with_scheduler(
+pub fn with_scheduler(
     scheduler: SchedulerMutex,
 ) -> impl Filter<Extract = (SchedulerMutex,), Error = Infallible> + Clone {
     any().map(move || scheduler.clone())
 }
 
-fn with_stream(
-    player: PlayerMutex,
-) -> impl Filter<Extract = (PlayerMutex,), Error = Infallible> + Clone {
-    any().map(move || player.clone())
+pub fn with_zones(
+    zones: ZonesMutex,
+) -> impl Filter<Extract = (ZonesMutex,), Error = Infallible> + Clone {
+    any().map(move || zones.clone())
 }
 
 fn with_id() -> impl Filter<Extract = (u32,), Error = Rejection> + Clone {
@@ -260,10 +594,39 @@ fn with_id() -> impl Filter<Extract = (u32,), Error = Rejection> + Clone {
         })
 }
 
-fn json_schedule() -> impl Filter<Extract = ((u32, String),), Error = Rejection> + Clone {
+fn json_normalize() -> impl Filter<Extract = ((u32, bool),), Error = Rejection> + Clone {
+    body::content_length_limit(1024 * 16).and(body::json())
+}
+
+fn json_trim() -> impl Filter<Extract = ((u32, f64, Option<f64>),), Error = Rejection> + Clone {
+    body::content_length_limit(1024 * 16).and(body::json())
+}
+
+fn json_announcement() -> impl Filter<Extract = (Announcement,), Error = Rejection> + Clone {
+    body::content_length_limit(1024 * 16).and(body::json())
+}
+
+fn json_rolls(
+) -> impl Filter<Extract = ((u32, Option<u32>, Option<u32>),), Error = Rejection> + Clone {
+    body::content_length_limit(1024 * 16).and(body::json())
+}
+
+fn json_zone() -> impl Filter<Extract = ((u32, Option<String>),), Error = Rejection> + Clone {
+    body::content_length_limit(1024 * 16).and(body::json())
+}
+
+fn json_generator() -> impl Filter<Extract = ((String, Generator),), Error = Rejection> + Clone {
+    body::content_length_limit(1024 * 16).and(body::json())
+}
+
+fn json_stream() -> impl Filter<Extract = ((String, String),), Error = Rejection> + Clone {
+    body::content_length_limit(1024 * 16).and(body::json())
+}
+
+fn json_schedule() -> impl Filter<Extract = (NewSchedule,), Error = Rejection> + Clone {
     body::content_length_limit(1024 * 16).and(body::json())
 }
 
-fn json_edit_schedule() -> impl Filter<Extract = ((u32, u32, String),), Error = Rejection> + Clone {
+fn json_edit_schedule() -> impl Filter<Extract = (EditedSchedule,), Error = Rejection> + Clone {
     body::content_length_limit(1024 * 16).and(body::json())
 }
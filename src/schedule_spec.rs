@@ -0,0 +1,275 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::str::FromStr;
+use utoipa::ToSchema;
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord, ToSchema)]
+pub enum Weekday {
+    Mon,
+    Tue,
+    Wed,
+    Thu,
+    Fri,
+    Sat,
+    Sun,
+}
+
+/// Repeats every `minutes` from `from` to `to` (both inclusive, "HH:MM" or "HH:MM:SS").
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, ToSchema)]
+pub struct Interval {
+    pub minutes: u32,
+    pub from: String,
+    pub to: String,
+}
+
+/// Human-friendly schedule, compiled into one or more cron expressions.
+/// Times are in the player's time zone, see `utils::time_zone`. An empty `days` list means
+/// every day of the week.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, ToSchema)]
+pub struct ScheduleSpec {
+    #[serde(default)]
+    pub days: Vec<Weekday>,
+    #[serde(default)]
+    pub times: Vec<String>,
+    #[serde(default)]
+    pub every: Option<Interval>,
+}
+
+/// Schedule as accepted by the API, either a raw cron expression or a structured spec,
+/// both evaluated in the player's time zone.
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+#[serde(untagged)]
+pub enum ScheduleDefinition {
+    Cron(String),
+    Spec(ScheduleSpec),
+}
+
+impl ScheduleDefinition {
+    /// Validates the definition and returns the cron expressions to store
+    /// (separated by `;`) together with the structured form, if any.
+    pub fn compile(self) -> Result<(String, Option<ScheduleSpec>), String> {
+        match self {
+            ScheduleDefinition::Cron(schedule) => {
+                let expressions = split_expressions(&schedule);
+                if expressions.is_empty() {
+                    return Err("schedule is empty".to_string());
+                }
+                for expression in expressions {
+                    validate_cron(expression)?;
+                }
+                Ok((schedule, None))
+            }
+            ScheduleDefinition::Spec(spec) => {
+                let expressions = spec.compile()?;
+                Ok((expressions.join(";"), Some(spec)))
+            }
+        }
+    }
+}
+
+impl ScheduleSpec {
+    pub fn compile(&self) -> Result<Vec<String>, String> {
+        let mut seconds_of_day = BTreeSet::new();
+        for time in self.times.iter() {
+            seconds_of_day.insert(parse_time(time)?);
+        }
+        if let Some(every) = &self.every {
+            if every.minutes == 0 || every.minutes > 24 * 60 {
+                return Err(format!("invalid interval: {} minutes", every.minutes));
+            }
+            let from = parse_time(&every.from)?;
+            let to = parse_time(&every.to)?;
+            if from > to {
+                return Err(format!(
+                    "interval start {} is after end {}",
+                    every.from, every.to
+                ));
+            }
+            seconds_of_day.extend((from..=to).step_by(every.minutes as usize * 60));
+        }
+        if seconds_of_day.is_empty() {
+            return Err("schedule does not contain any time".to_string());
+        }
+
+        let days = self.days.iter().collect::<BTreeSet<&Weekday>>();
+        let day_of_week = if days.is_empty() || days.len() == 7 {
+            "*".to_string()
+        } else {
+            days.iter()
+                .map(|d| format!("{:?}", d))
+                .collect::<Vec<String>>()
+                .join(",")
+        };
+
+        // times sharing the same minute and second collapse into one expression
+        let mut hours_by_minute: BTreeMap<(u32, u32), Vec<u32>> = BTreeMap::new();
+        for time in seconds_of_day {
+            hours_by_minute
+                .entry((time / 60 % 60, time % 60))
+                .or_default()
+                .push(time / 3600);
+        }
+        let expressions = hours_by_minute
+            .iter()
+            .map(|((minute, second), hours)| {
+                let hours = hours
+                    .iter()
+                    .map(|h| h.to_string())
+                    .collect::<Vec<String>>()
+                    .join(",");
+                format!("{} {} {} * * {}", second, minute, hours, day_of_week)
+            })
+            .collect::<Vec<String>>();
+        for expression in expressions.iter() {
+            validate_cron(expression)?;
+        }
+        Ok(expressions)
+    }
+}
+
+pub fn split_expressions(schedule: &str) -> Vec<&str> {
+    schedule
+        .split(';')
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+fn validate_cron(expression: &str) -> Result<(), String> {
+    cron::Schedule::from_str(expression)
+        .map(|_| ())
+        .map_err(|e| format!("invalid cron expression '{}': {}", expression, e))
+}
+
+fn parse_time(time: &str) -> Result<u32, String> {
+    let parts = time
+        .split(':')
+        .map(|p| p.trim().parse::<u32>())
+        .collect::<Result<Vec<u32>, _>>()
+        .map_err(|_| format!("invalid time: {}", time))?;
+    match parts.as_slice() {
+        [h, m] if *h < 24 && *m < 60 => Ok(h * 3600 + m * 60),
+        [h, m, s] if *h < 24 && *m < 60 && *s < 60 => Ok(h * 3600 + m * 60 + s),
+        _ => Err(format!("invalid time: {}", time)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec(days: Vec<Weekday>, times: &[&str], every: Option<(u32, &str, &str)>) -> ScheduleSpec {
+        ScheduleSpec {
+            days,
+            times: times.iter().map(|t| t.to_string()).collect(),
+            every: every.map(|(minutes, from, to)| Interval {
+                minutes,
+                from: from.to_string(),
+                to: to.to_string(),
+            }),
+        }
+    }
+
+    #[test]
+    fn times_sharing_minute_and_second_collapse() {
+        let compiled = spec(vec![], &["09:00", "08:00", "08:15:30", "17:00"], None).compile();
+        assert_eq!(compiled.unwrap(), vec!["0 0 8,9,17 * * *", "30 15 8 * * *"]);
+    }
+
+    #[test]
+    fn duplicate_times_are_merged() {
+        let compiled = spec(vec![], &["08:00", "8:00:00"], None).compile();
+        assert_eq!(compiled.unwrap(), vec!["0 0 8 * * *"]);
+    }
+
+    #[test]
+    fn interval_includes_both_ends() {
+        let compiled = spec(vec![], &[], Some((30, "08:00", "09:00"))).compile();
+        assert_eq!(compiled.unwrap(), vec!["0 0 8,9 * * *", "0 30 8 * * *"]);
+    }
+
+    #[test]
+    fn interval_combines_with_times() {
+        let compiled = spec(vec![], &["12:30"], Some((60, "10:00", "11:30"))).compile();
+        assert_eq!(compiled.unwrap(), vec!["0 0 10,11 * * *", "0 30 12 * * *"]);
+    }
+
+    #[test]
+    fn interval_start_after_end_is_rejected() {
+        let compiled = spec(vec![], &[], Some((15, "10:00", "09:00"))).compile();
+        assert_eq!(
+            compiled.unwrap_err(),
+            "interval start 10:00 is after end 09:00"
+        );
+    }
+
+    #[test]
+    fn interval_length_is_checked() {
+        assert!(spec(vec![], &[], Some((0, "08:00", "09:00")))
+            .compile()
+            .is_err());
+        assert!(spec(vec![], &[], Some((24 * 60 + 1, "08:00", "09:00")))
+            .compile()
+            .is_err());
+    }
+
+    #[test]
+    fn days_are_sorted_and_deduplicated() {
+        let days = vec![Weekday::Fri, Weekday::Mon, Weekday::Fri];
+        let compiled = spec(days, &["08:00"], None).compile();
+        assert_eq!(compiled.unwrap(), vec!["0 0 8 * * Mon,Fri"]);
+    }
+
+    #[test]
+    fn every_day_of_the_week_is_a_wildcard() {
+        let days = vec![
+            Weekday::Mon,
+            Weekday::Tue,
+            Weekday::Wed,
+            Weekday::Thu,
+            Weekday::Fri,
+            Weekday::Sat,
+            Weekday::Sun,
+        ];
+        let compiled = spec(days, &["08:00"], None).compile();
+        assert_eq!(compiled.unwrap(), vec!["0 0 8 * * *"]);
+    }
+
+    #[test]
+    fn invalid_times_are_rejected() {
+        for time in ["24:00", "08:60", "08:00:60", "8", "08:00:00:00", "eight"] {
+            assert_eq!(
+                spec(vec![], &[time], None).compile().unwrap_err(),
+                format!("invalid time: {}", time)
+            );
+        }
+    }
+
+    #[test]
+    fn spec_without_times_is_rejected() {
+        assert!(spec(vec![Weekday::Mon], &[], None).compile().is_err());
+    }
+
+    #[test]
+    fn definition_keeps_spec_and_joins_expressions() {
+        let definition = ScheduleDefinition::Spec(spec(vec![], &["08:00", "08:30"], None));
+        let (schedule, spec) = definition.compile().unwrap();
+        assert_eq!(schedule, "0 0 8 * * *;0 30 8 * * *");
+        assert!(spec.is_some());
+    }
+
+    #[test]
+    fn cron_definition_is_validated() {
+        let (schedule, spec) = ScheduleDefinition::Cron("0 0 8 * * *; 0 0 9 * * Mon".to_string())
+            .compile()
+            .unwrap();
+        assert_eq!(schedule, "0 0 8 * * *; 0 0 9 * * Mon");
+        assert!(spec.is_none());
+        assert!(ScheduleDefinition::Cron("0 0 25 * * *".to_string())
+            .compile()
+            .is_err());
+        assert!(ScheduleDefinition::Cron(" ; ".to_string())
+            .compile()
+            .is_err());
+    }
+}
@@ -1,22 +1,28 @@
+use chrono::Utc;
 use tokio_cron_scheduler::{Job, JobScheduler};
+use tracing::{error, info, info_span, Instrument};
 
+use crate::metrics::{trigger_latency, METRICS};
 use crate::models::{ActiveSchedule, Activity};
-use crate::PlayerMutex;
+use crate::utils::time_zone;
 use crate::StateMutex;
+use crate::ZonesMutex;
 
 pub struct Scheduler {
     scheduler: JobScheduler,
     active_schedules: Vec<ActiveSchedule>,
-    player: PlayerMutex,
+    running: bool,
+    zones: ZonesMutex,
     state: StateMutex,
 }
 
 impl Scheduler {
-    pub async fn new(player: PlayerMutex, state: StateMutex) -> Scheduler {
+    pub async fn new(zones: ZonesMutex, state: StateMutex) -> Scheduler {
         Scheduler {
             scheduler: JobScheduler::new().await.unwrap(),
             active_schedules: vec![],
-            player,
+            running: false,
+            zones,
             state,
         }
     }
@@ -25,24 +31,76 @@ impl Scheduler {
         let state = self.state.lock().await;
         let schedule = state.get_schedule(schedule_id).unwrap().clone();
 
-        let player = self.player.clone();
-        let media = state.get_media(schedule.file_id).unwrap().clone();
-        let job = Job::new_async(schedule.schedule.as_str(), move |_uuid, _l| {
-            let player = player.clone();
-            let media = media.clone();
-            Box::pin(async move {
-                println!("Triggered schedule: {}", schedule.id);
-                let media = media.clone();
-                let player = player.lock().await;
-                player.play(&media);
+        let mut job_ids = vec![];
+        for expression in schedule.cron_expressions() {
+            let zones = self.zones.clone();
+            let state = self.state.clone();
+            let cron = expression.to_string();
+            // a named zone rather than a fixed offset, so daylight saving
+            // changes apply to the next run without adding the job again
+            let job = Job::new_async_tz(expression, time_zone(), move |_uuid, _l| {
+                let zones = zones.clone();
+                let state = state.clone();
+                let cron = cron.clone();
+                let span = info_span!("schedule", id = schedule_id);
+                Box::pin(
+                    async move {
+                        info!("Triggered schedule: {}", schedule_id);
+                        // looked up on every run so edited schedules and replaced files apply
+                        let state = state.lock().await;
+                        let Some(schedule) = state
+                            .get_schedule(schedule_id)
+                            .filter(|schedule| schedule.activity == Activity::Active)
+                        else {
+                            info!("Schedule {} is gone or inactive, skipping", schedule_id);
+                            METRICS.schedules_suppressed.inc();
+                            return;
+                        };
+                        let zone = schedule.zone.clone();
+                        let sequence = state.get_sequence(
+                            schedule.file_id,
+                            schedule.pre_roll,
+                            schedule.post_roll,
+                        );
+                        drop(state);
+                        match sequence {
+                            Some(sequence) => {
+                                let zones = zones.lock().await;
+                                // an announcement in the wrong zone beats a missed one
+                                let player = zones.get(zone.as_deref()).unwrap_or_else(|| {
+                                    error!("zone {:?} not found, using the default zone", zone);
+                                    zones.get(None).unwrap()
+                                });
+                                match player.play_sequence(&sequence) {
+                                    Ok(()) => {
+                                        METRICS.schedules_fired.inc();
+                                        if let Some(latency) = trigger_latency(&cron, Utc::now()) {
+                                            METRICS.trigger_latency.observe(latency);
+                                        }
+                                    }
+                                    Err(e) => {
+                                        METRICS.schedules_failed.inc();
+                                        error!("error playing schedule {}: {}", schedule_id, e);
+                                    }
+                                }
+                            }
+                            None => {
+                                METRICS.schedules_failed.inc();
+                                error!("media of schedule {} not found", schedule_id)
+                            }
+                        }
+                    }
+                    .instrument(span),
+                )
             })
-        })
-        .unwrap();
+            .unwrap();
+            job_ids.push(job.guid());
+            self.scheduler.add(job).await.unwrap();
+        }
         self.active_schedules.push(ActiveSchedule {
-            schedule_id: schedule.id,
-            job_id: job.guid(),
+            schedule_id,
+            job_ids,
         });
-        self.scheduler.add(job).await.unwrap();
 
         drop(state);
         let mut state = self.state.lock().await;
@@ -52,22 +110,33 @@ impl Scheduler {
             .find(|s| s.id == schedule_id)
             .unwrap()
             .activity = Activity::Active;
-        println!("Added schedule: {} as active", schedule_id);
+        info!("Added schedule: {} as active", schedule_id);
         state.save_schedules();
     }
 
+    pub fn is_running(&self) -> bool {
+        self.running
+    }
+
+    pub fn active_schedules(&self) -> usize {
+        self.active_schedules.len()
+    }
+
+    pub fn active_jobs(&self) -> usize {
+        self.active_schedules.iter().map(|s| s.job_ids.len()).sum()
+    }
+
     pub async fn remove(&mut self, id: u32) {
-        println!("Removing schedule: {} from active", id);
+        info!("Removing schedule: {} from active", id);
         let active_schedule = self
             .active_schedules
             .iter()
             .find(|s| s.schedule_id == id)
             .unwrap();
-        self.scheduler
-            .remove(&active_schedule.job_id)
-            .await
-            .unwrap();
-        println!("Removed schedule: {} from active", id);
+        for job_id in active_schedule.job_ids.iter() {
+            self.scheduler.remove(job_id).await.unwrap();
+        }
+        info!("Removed schedule: {} from active", id);
         self.active_schedules.retain(|s| s.schedule_id != id);
         let mut state = self.state.lock().await;
         state
@@ -85,7 +154,7 @@ impl Scheduler {
     }
 
     pub async fn load(&mut self) {
-        println!("Loading schedules");
+        info!("Loading schedules");
         let schedules = self.state.lock().await.schedules.clone();
         for schedule in schedules
             .iter()
@@ -96,12 +165,14 @@ impl Scheduler {
     }
 
     pub async fn start(&mut self) {
-        println!("Starting scheduler");
+        info!("Starting scheduler");
         self.scheduler.start().await.unwrap();
+        self.running = true;
     }
 
     pub async fn stop(&mut self) {
-        println!("Stopping scheduler");
+        info!("Stopping scheduler");
         self.scheduler.shutdown().await.unwrap();
+        self.running = false;
     }
 }
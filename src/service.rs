@@ -0,0 +1,40 @@
+use sd_notify::NotifyState;
+use std::time::Duration;
+use tokio::signal::unix::{signal, SignalKind};
+use tracing::{info, warn};
+
+use crate::StateMutex;
+
+/// Resolves on SIGTERM (systemd stopping the unit) or SIGINT (Ctrl-C).
+pub async fn shutdown_signal() {
+    let mut terminate = signal(SignalKind::terminate()).unwrap();
+    tokio::select! {
+        _ = terminate.recv() => info!("Received SIGTERM"),
+        _ = tokio::signal::ctrl_c() => info!("Received SIGINT"),
+    }
+}
+
+/// Tells systemd about the service state, a no-op when not started by it.
+pub fn notify(state: NotifyState) {
+    if let Err(e) = sd_notify::notify(false, &[state]) {
+        warn!("error notifying systemd: {}", e);
+    }
+}
+
+/// Pings the systemd watchdog if the unit sets `WatchdogSec`. Each ping
+/// takes the state lock, so a deadlock gets the service restarted as well.
+pub fn spawn_watchdog(state: StateMutex) {
+    let mut usec = 0;
+    if !sd_notify::watchdog_enabled(false, &mut usec) {
+        return;
+    }
+    let interval = Duration::from_micros(usec) / 2;
+    info!("Pinging systemd watchdog every {:?}", interval);
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(interval).await;
+            drop(state.lock().await);
+            notify(NotifyState::Watchdog);
+        }
+    });
+}
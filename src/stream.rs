@@ -0,0 +1,428 @@
+use rodio::Source;
+use std::io::{self, Read};
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender, TryRecvError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::DecoderOptions;
+use symphonia::core::errors::Error;
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::{MediaSourceStream, ReadOnlySource};
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+use tracing::{error, info, info_span, warn};
+
+/// Decoded packets held ahead of playback, a few seconds for typical streams.
+const BUFFERED_CHUNKS: usize = 200;
+const RECONNECT_ATTEMPTS: u32 = 10;
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Stream currently being played and the last title announced by the server.
+#[derive(Clone, Debug)]
+pub struct StreamInfo {
+    pub url: String,
+    pub title: Arc<Mutex<Option<String>>>,
+}
+
+pub fn validate_url(url: &str) -> Result<(), String> {
+    if url.starts_with("http://") || url.starts_with("https://") {
+        Ok(())
+    } else {
+        Err(format!("unsupported stream url: {}", url))
+    }
+}
+
+struct Chunk {
+    channels: u16,
+    sample_rate: u32,
+    samples: Vec<i16>,
+}
+
+impl Chunk {
+    /// 20 ms of silence, played while waiting for the network.
+    fn silence(channels: u16, sample_rate: u32) -> Chunk {
+        Chunk {
+            channels,
+            sample_rate,
+            samples: vec![0; (sample_rate / 50) as usize * channels as usize],
+        }
+    }
+}
+
+/// Plays an HTTP(S) audio stream. Network and decoding happen on a separate
+/// thread, so a stalled connection produces silence instead of blocking output.
+pub struct HttpStream {
+    receiver: Receiver<Chunk>,
+    current: Chunk,
+    position: usize,
+    finished: bool,
+}
+
+impl HttpStream {
+    pub fn open(info: &StreamInfo) -> HttpStream {
+        let (sender, receiver) = sync_channel(BUFFERED_CHUNKS);
+        let url = info.url.clone();
+        let title = info.title.clone();
+        let span = info_span!("stream", url = %url);
+        thread::spawn(move || span.in_scope(|| receive(&url, &sender, &title)));
+        HttpStream {
+            receiver,
+            current: Chunk::silence(2, 44100),
+            position: 0,
+            finished: false,
+        }
+    }
+
+    fn advance(&mut self) {
+        self.position = 0;
+        self.current = match self.receiver.try_recv() {
+            Ok(chunk) if !chunk.samples.is_empty() => chunk,
+            Ok(_) | Err(TryRecvError::Empty) => {
+                Chunk::silence(self.current.channels, self.current.sample_rate)
+            }
+            Err(TryRecvError::Disconnected) => {
+                self.finished = true;
+                return;
+            }
+        };
+    }
+}
+
+impl Iterator for HttpStream {
+    type Item = i16;
+
+    fn next(&mut self) -> Option<i16> {
+        if self.finished {
+            return None;
+        }
+        let sample = self.current.samples[self.position];
+        self.position += 1;
+        if self.position >= self.current.samples.len() {
+            self.advance();
+        }
+        Some(sample)
+    }
+}
+
+impl Source for HttpStream {
+    fn current_frame_len(&self) -> Option<usize> {
+        if self.finished {
+            return Some(0);
+        }
+        Some(self.current.samples.len() - self.position)
+    }
+
+    fn channels(&self) -> u16 {
+        self.current.channels
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.current.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+/// Keeps the stream going until playback is stopped, reconnecting with
+/// backoff whenever the connection drops.
+fn receive(url: &str, sender: &SyncSender<Chunk>, title: &Arc<Mutex<Option<String>>>) {
+    let mut attempts = 0;
+    loop {
+        match decode(url, sender, title) {
+            Ok(Received::Stopped) => return,
+            Ok(Received::Data) => attempts = 0,
+            Ok(Received::Nothing) => {}
+            Err(e) => warn!("stream {} failed: {}", url, e),
+        }
+        attempts += 1;
+        if attempts > RECONNECT_ATTEMPTS {
+            error!("giving up on stream: {}", url);
+            return;
+        }
+        let backoff = backoff(attempts);
+        info!("reconnecting to {} in {:?}", url, backoff);
+        thread::sleep(backoff);
+    }
+}
+
+/// Delay before the given reconnect attempt, doubling from two seconds.
+fn backoff(attempts: u32) -> Duration {
+    Duration::from_secs(1 << attempts.min(5)).min(MAX_BACKOFF)
+}
+
+enum Received {
+    /// Playback was stopped, the receiving end is gone.
+    Stopped,
+    Data,
+    Nothing,
+}
+
+fn decode(
+    url: &str,
+    sender: &SyncSender<Chunk>,
+    title: &Arc<Mutex<Option<String>>>,
+) -> Result<Received, String> {
+    info!("connecting to stream: {}", url);
+    let response = ureq::AgentBuilder::new()
+        .timeout_connect(TIMEOUT)
+        .timeout_read(TIMEOUT)
+        .build()
+        .get(url)
+        .set("Icy-MetaData", "1")
+        .call()
+        .map_err(|e| e.to_string())?;
+
+    let mut hint = Hint::new();
+    match response.content_type() {
+        "audio/mpeg" | "audio/mp3" => hint.with_extension("mp3"),
+        "audio/ogg" | "application/ogg" => hint.with_extension("ogg"),
+        "audio/aac" | "audio/aacp" => hint.with_extension("aac"),
+        _ => &mut hint,
+    };
+    let metaint = response
+        .header("icy-metaint")
+        .and_then(|interval| interval.parse().ok());
+    let reader = IcyReader {
+        inner: response.into_reader(),
+        metaint,
+        remaining: metaint.unwrap_or(0),
+        title: title.clone(),
+    };
+    let stream = MediaSourceStream::new(Box::new(ReadOnlySource::new(reader)), Default::default());
+
+    let mut format = symphonia::default::get_probe()
+        .format(
+            &hint,
+            stream,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )
+        .map_err(|e| e.to_string())?
+        .format;
+    let track = format.default_track().ok_or("stream has no audio track")?;
+    let track_id = track.id;
+    let mut decoder = symphonia::default::get_codecs()
+        .make(&track.codec_params, &DecoderOptions::default())
+        .map_err(|e| e.to_string())?;
+
+    let mut received = Received::Nothing;
+    loop {
+        let packet = match format.next_packet() {
+            Ok(packet) => packet,
+            Err(Error::IoError(e)) if e.kind() == io::ErrorKind::UnexpectedEof => {
+                info!("stream ended: {}", url);
+                return Ok(received);
+            }
+            Err(e) => return Err(e.to_string()),
+        };
+        if packet.track_id() != track_id {
+            continue;
+        }
+        let decoded = match decoder.decode(&packet) {
+            Ok(decoded) => decoded,
+            // corrupt frames happen on live streams, skip them
+            Err(Error::DecodeError(e)) => {
+                warn!("stream decode error: {}", e);
+                continue;
+            }
+            Err(e) => return Err(e.to_string()),
+        };
+        let spec = *decoded.spec();
+        let mut buffer = SampleBuffer::<i16>::new(decoded.capacity() as u64, spec);
+        buffer.copy_interleaved_ref(decoded);
+        let chunk = Chunk {
+            channels: spec.channels.count() as u16,
+            sample_rate: spec.rate,
+            samples: buffer.samples().to_vec(),
+        };
+        if sender.send(chunk).is_err() {
+            return Ok(Received::Stopped);
+        }
+        received = Received::Data;
+    }
+}
+
+/// Strips shoutcast/icecast metadata blocks out of the audio data and keeps the stream title.
+struct IcyReader<R: Read> {
+    inner: R,
+    metaint: Option<usize>,
+    /// Audio bytes left until the next metadata block.
+    remaining: usize,
+    title: Arc<Mutex<Option<String>>>,
+}
+
+impl<R: Read> IcyReader<R> {
+    fn read_metadata(&mut self) -> io::Result<()> {
+        let mut length = [0u8; 1];
+        self.inner.read_exact(&mut length)?;
+        let mut metadata = vec![0u8; length[0] as usize * 16];
+        self.inner.read_exact(&mut metadata)?;
+        let metadata = String::from_utf8_lossy(&metadata);
+        if let Some(title) = metadata
+            .split(';')
+            .find_map(|field| field.trim().strip_prefix("StreamTitle="))
+        {
+            let title = title.trim_matches('\'').to_string();
+            info!("stream title: {}", title);
+            *self.title.lock().unwrap() = Some(title);
+        }
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for IcyReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let metaint = match self.metaint {
+            Some(metaint) => metaint,
+            None => return self.inner.read(buf),
+        };
+        if self.remaining == 0 {
+            self.read_metadata()?;
+            self.remaining = metaint;
+        }
+        let length = buf.len().min(self.remaining);
+        let read = self.inner.read(&mut buf[..length])?;
+        self.remaining -= read;
+        Ok(read)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Cursor, Write};
+    use std::net::TcpListener;
+    use std::sync::mpsc::channel;
+    use std::time::Instant;
+
+    const MP3: &[u8] = include_bytes!("../media/tripple_kill.mp3");
+    const METAINT: usize = 1024;
+
+    /// Interleaves `audio` with icy metadata blocks, the first one carrying `title`.
+    fn icy(audio: &[u8], title: &str) -> Vec<u8> {
+        let mut body = Vec::new();
+        for (index, chunk) in audio.chunks(METAINT).enumerate() {
+            body.extend_from_slice(chunk);
+            if chunk.len() < METAINT {
+                break;
+            }
+            if index == 0 {
+                let mut metadata = format!("StreamTitle='{}';StreamUrl='';", title).into_bytes();
+                metadata.resize(metadata.len().div_ceil(16) * 16, 0);
+                body.push((metadata.len() / 16) as u8);
+                body.extend_from_slice(&metadata);
+            } else {
+                body.push(0);
+            }
+        }
+        body
+    }
+
+    #[test]
+    fn icy_reader_strips_metadata() {
+        let audio: Vec<u8> = (0..4000).map(|i| i as u8).collect();
+        let title = Arc::new(Mutex::new(None));
+        let mut reader = IcyReader {
+            inner: Cursor::new(icy(&audio, "Artist - Song")),
+            metaint: Some(METAINT),
+            remaining: METAINT,
+            title: title.clone(),
+        };
+        let mut read = Vec::new();
+        reader.read_to_end(&mut read).unwrap();
+        assert_eq!(read, audio);
+        assert_eq!(title.lock().unwrap().as_deref(), Some("Artist - Song"));
+    }
+
+    #[test]
+    fn icy_reader_passes_plain_streams_through() {
+        let title = Arc::new(Mutex::new(None));
+        let mut reader = IcyReader {
+            inner: Cursor::new(MP3),
+            metaint: None,
+            remaining: 0,
+            title: title.clone(),
+        };
+        let mut read = Vec::new();
+        reader.read_to_end(&mut read).unwrap();
+        assert_eq!(read, MP3);
+        assert!(title.lock().unwrap().is_none());
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_the_maximum() {
+        assert_eq!(backoff(1), Duration::from_secs(2));
+        assert_eq!(backoff(4), Duration::from_secs(16));
+        assert_eq!(backoff(5), MAX_BACKOFF);
+        assert_eq!(backoff(RECONNECT_ATTEMPTS), MAX_BACKOFF);
+    }
+
+    #[test]
+    fn stream_reconnects_after_the_server_drops_it() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/stream", listener.local_addr().unwrap());
+        let (accepted, connections) = channel();
+        let (serve, go) = channel::<()>();
+        let server = thread::spawn(move || {
+            for number in 0..3 {
+                let (mut socket, _) = listener.accept().unwrap();
+                let mut request = BufReader::new(socket.try_clone().unwrap());
+                let mut metadata = false;
+                loop {
+                    let mut line = String::new();
+                    request.read_line(&mut line).unwrap();
+                    metadata |= line.eq_ignore_ascii_case("icy-metadata: 1\r\n");
+                    if line == "\r\n" {
+                        break;
+                    }
+                }
+                accepted.send((Instant::now(), metadata)).unwrap();
+                go.recv().unwrap();
+                // a connection the client gave up on is fine to fail
+                let _ = write!(
+                    socket,
+                    "HTTP/1.0 200 OK\r\ncontent-type: audio/mpeg\r\nicy-metaint: {}\r\n\r\n",
+                    METAINT
+                )
+                .and_then(|_| socket.write_all(&icy(MP3, &format!("Song {}", number))));
+            }
+        });
+
+        let (sender, receiver) = sync_channel(BUFFERED_CHUNKS);
+        let title = Arc::new(Mutex::new(None));
+        let client = {
+            let title = title.clone();
+            thread::spawn(move || receive(&url, &sender, &title))
+        };
+        let wait = Duration::from_secs(10);
+
+        let (first, metadata) = connections.recv_timeout(wait).unwrap();
+        assert!(metadata);
+        serve.send(()).unwrap();
+        let (second, _) = connections.recv_timeout(wait).unwrap();
+        assert!(second - first >= backoff(1));
+        assert_eq!(title.lock().unwrap().as_deref(), Some("Song 0"));
+        let chunks = receiver
+            .try_iter()
+            .filter(|c| !c.samples.is_empty())
+            .count();
+        assert!(chunks > 0);
+
+        serve.send(()).unwrap();
+        let (third, _) = connections.recv_timeout(wait).unwrap();
+        // the attempts are reset after a connection delivered audio
+        assert!(third - second >= backoff(1));
+        assert!(third - second < backoff(2));
+        assert_eq!(title.lock().unwrap().as_deref(), Some("Song 1"));
+
+        // once playback is stopped the receiver gives up on the stream
+        drop(receiver);
+        serve.send(()).unwrap();
+        client.join().unwrap();
+        server.join().unwrap();
+    }
+}
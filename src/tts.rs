@@ -0,0 +1,168 @@
+use std::path::Path;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use tracing::debug;
+
+const TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Renders `text` to a WAV file at `output` by running the configured engine.
+/// The command is split on whitespace, `{output}` is replaced by the target
+/// path and the text is written to the engine's stdin.
+pub async fn synthesize(command: &str, text: &str, output: &Path) -> Result<(), String> {
+    synthesize_within(command, text, output, TIMEOUT).await
+}
+
+async fn synthesize_within(
+    command: &str,
+    text: &str,
+    output: &Path,
+    timeout: Duration,
+) -> Result<(), String> {
+    let mut args = command
+        .split_whitespace()
+        .map(|arg| arg.replace("{output}", &output.to_string_lossy()));
+    let program = args.next().ok_or("no text to speech engine configured")?;
+    debug!("synthesizing speech with: {}", program);
+
+    let mut child = Command::new(&program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| format!("failed to start {}: {}", program, e))?;
+    let mut stdin = child.stdin.take().ok_or("failed to open engine stdin")?;
+    // an engine that stops reading its input would block the write forever,
+    // dropping the child on timeout kills it
+    let run = async move {
+        stdin
+            .write_all(text.as_bytes())
+            .await
+            .map_err(|e| e.to_string())?;
+        drop(stdin);
+        child.wait_with_output().await.map_err(|e| e.to_string())
+    };
+    let output = tokio::time::timeout(timeout, run)
+        .await
+        .map_err(|_| format!("{} timed out", program))??;
+    if !output.status.success() {
+        return Err(format!(
+            "{} failed with {}: {}",
+            program,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+    use std::path::PathBuf;
+    use std::time::Instant;
+
+    /// Directory holding an engine script with the given body.
+    fn engine(body: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("tts-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let script = dir.join("engine.sh");
+        std::fs::write(&script, format!("#!/bin/sh\n{}\n", body)).unwrap();
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+        dir
+    }
+
+    #[tokio::test]
+    async fn engine_writes_wav_from_stdin() {
+        let dir = engine(r#"cat > "$1.txt"; cp "$(dirname "$0")/speech.wav" "$1""#);
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: 16000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(dir.join("speech.wav"), spec).unwrap();
+        for _ in 0..1600 {
+            writer.write_sample(0i16).unwrap();
+        }
+        writer.finalize().unwrap();
+
+        let output = dir.join("out.wav");
+        let command = format!("{} {{output}}", dir.join("engine.sh").display());
+        synthesize(&command, "Attention please", &output)
+            .await
+            .unwrap();
+
+        let reader = hound::WavReader::open(&output).unwrap();
+        assert_eq!(reader.spec(), spec);
+        assert_eq!(reader.duration(), 1600);
+        let text = std::fs::read_to_string(dir.join("out.wav.txt")).unwrap();
+        assert_eq!(text, "Attention please");
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn failing_engine_reports_stderr() {
+        let dir = engine("cat > /dev/null; echo unknown voice >&2; exit 3");
+        let command = dir.join("engine.sh").display().to_string();
+        let error = synthesize(&command, "hello", &dir.join("out.wav"))
+            .await
+            .unwrap_err();
+        assert!(error.contains("failed with"), "{}", error);
+        assert!(error.ends_with(": unknown voice"), "{}", error);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn missing_engine_fails_to_start() {
+        let error = synthesize("/nonexistent/engine", "hello", Path::new("out.wav"))
+            .await
+            .unwrap_err();
+        assert!(error.starts_with("failed to start"), "{}", error);
+        assert!(synthesize("  ", "hello", Path::new("out.wav"))
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn stuck_engine_times_out() {
+        let dir = engine("cat > /dev/null; sleep 30");
+        let command = dir.join("engine.sh").display().to_string();
+        let started = Instant::now();
+        let error = synthesize_within(
+            &command,
+            "hello",
+            &dir.join("out.wav"),
+            Duration::from_millis(300),
+        )
+        .await
+        .unwrap_err();
+        assert!(error.ends_with("timed out"), "{}", error);
+        assert!(started.elapsed() < Duration::from_secs(10));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn engine_not_reading_stdin_times_out() {
+        // more text than fits in the pipe, so the write itself blocks
+        let dir = engine("sleep 30");
+        let command = dir.join("engine.sh").display().to_string();
+        let text = "a".repeat(4 * 1024 * 1024);
+        let started = Instant::now();
+        let error = synthesize_within(
+            &command,
+            &text,
+            &dir.join("out.wav"),
+            Duration::from_millis(300),
+        )
+        .await
+        .unwrap_err();
+        assert!(error.ends_with("timed out"), "{}", error);
+        assert!(started.elapsed() < Duration::from_secs(10));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
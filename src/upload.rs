@@ -0,0 +1,104 @@
+use sha2::{Digest, Sha256};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::fs::File;
+use tokio::io::AsyncWriteExt;
+use tracing::{debug, info, warn};
+use uuid::Uuid;
+
+use crate::consts::MEDIA_PATH;
+use crate::metrics::METRICS;
+use crate::models::ActiveUpload;
+use crate::StateMutex;
+
+/// Upload being streamed into a temporary file inside `MEDIA_PATH`.
+/// Unless persisted, the partial file is removed when this is dropped,
+/// including when the client disconnects mid-upload.
+pub struct PartialUpload {
+    id: Uuid,
+    path: PathBuf,
+    file: File,
+    hasher: Sha256,
+    size: u64,
+    received: Arc<AtomicU64>,
+    state: StateMutex,
+    persisted: bool,
+}
+
+impl PartialUpload {
+    pub async fn create(name: &str, state: StateMutex) -> io::Result<PartialUpload> {
+        let dir = Path::new(MEDIA_PATH).join(".upload");
+        tokio::fs::create_dir_all(&dir).await?;
+        let id = Uuid::new_v4();
+        let path = dir.join(id.to_string()).with_extension("part");
+        debug!("receiving file {} into: {}", name, path.display());
+        let file = File::create(&path).await?;
+        let received = Arc::new(AtomicU64::new(0));
+        state.lock().await.uploads.push(ActiveUpload {
+            id: id.to_string(),
+            name: name.to_string(),
+            received: received.clone(),
+        });
+        Ok(PartialUpload {
+            id,
+            path,
+            file,
+            hasher: Sha256::new(),
+            size: 0,
+            received,
+            state,
+            persisted: false,
+        })
+    }
+
+    pub async fn write(&mut self, chunk: &[u8]) -> io::Result<()> {
+        self.file.write_all(chunk).await?;
+        self.hasher.update(chunk);
+        self.size += chunk.len() as u64;
+        METRICS.upload_bytes.inc_by(chunk.len() as u64);
+        self.received.store(self.size, Ordering::Relaxed);
+        Ok(())
+    }
+
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn hash(&self) -> String {
+        format!("{:x}", self.hasher.clone().finalize())
+    }
+
+    /// Moves the finished upload into the library and returns its final path.
+    pub async fn persist(mut self, file_ending: &str) -> io::Result<String> {
+        self.file.sync_all().await?;
+        let path = Path::new(MEDIA_PATH)
+            .join(self.id.to_string())
+            .with_extension(file_ending);
+        tokio::fs::rename(&self.path, &path).await?;
+        self.persisted = true;
+        info!("created file: {}", path.display());
+        Ok(path.to_string_lossy().to_string())
+    }
+}
+
+impl Drop for PartialUpload {
+    fn drop(&mut self) {
+        if !self.persisted {
+            info!("discarding partial upload: {}", self.path.display());
+            if let Err(e) = std::fs::remove_file(&self.path) {
+                warn!("error deleting partial upload: {}", e);
+            }
+        }
+        let id = self.id.to_string();
+        let state = self.state.clone();
+        tokio::spawn(async move {
+            state.lock().await.uploads.retain(|u| u.id != id);
+        });
+    }
+}
@@ -1,20 +1,40 @@
+use chrono_tz::Tz;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::io;
 use std::path::Path;
+use std::sync::OnceLock;
 use std::{fs::File, io::BufReader};
+use tracing::{debug, info, warn};
 
-use crate::consts::MEDIA_PATH;
+use crate::consts::LOG_FORMAT;
+use crate::consts::LOG_LEVEL;
+use crate::consts::MAX_UPLOAD_SIZE;
 use crate::consts::RESOURCE_PATH;
+use crate::consts::TARGET_LOUDNESS;
+use crate::consts::TTS_COMMAND;
+use crate::consts::ZONES;
 use crate::models::{MediaFile, Schedule};
 
+/// Writes through a temporary file, so being killed mid-write never
+/// leaves a truncated file behind.
+fn write_json<T: Serialize>(path: &Path, value: &T) -> io::Result<()> {
+    let temp = path.with_extension("json.tmp");
+    let file = File::create(&temp)?;
+    serde_json::to_writer(&file, value)?;
+    file.sync_all()?;
+    std::fs::rename(temp, path)
+}
+
 pub fn write_media_files(files: &Vec<MediaFile>) {
     let path = Path::new(RESOURCE_PATH).join("media.json");
-    println!("writing media files to: {}", path.display());
-    let file = File::create(path).unwrap();
-    serde_json::to_writer(file, files).unwrap();
+    debug!("writing media files to: {}", path.display());
+    write_json(&path, files).unwrap();
 }
 
 pub fn load_media_files() -> Vec<MediaFile> {
     let path = Path::new(RESOURCE_PATH).join("media.json");
-    println!("loading media files from: {}", path.display());
+    debug!("loading media files from: {}", path.display());
     let file = File::open(path).unwrap();
     let reader = BufReader::new(file);
     let files: Vec<MediaFile> = serde_json::from_reader(reader).unwrap();
@@ -23,46 +43,155 @@ pub fn load_media_files() -> Vec<MediaFile> {
 
 pub fn write_schedules(schedules: &Vec<Schedule>) {
     let path = Path::new(RESOURCE_PATH).join("schedules.json");
-    println!("writing schedules to: {}", path.display());
-    let file = File::create(path).unwrap();
-    serde_json::to_writer(file, schedules).unwrap();
+    debug!("writing schedules to: {}", path.display());
+    write_json(&path, schedules).unwrap();
 }
 
 pub fn load_schedules() -> Vec<Schedule> {
     let path = Path::new(RESOURCE_PATH).join("schedules.json");
-    println!("loading schedules from: {}", path.display());
+    debug!("loading schedules from: {}", path.display());
     let file = File::open(path).unwrap();
     let reader = BufReader::new(file);
     let files: Vec<Schedule> = serde_json::from_reader(reader).unwrap();
     files
 }
 
-pub async fn write_file(file_name: &str, file_ending: &str, data: &Vec<u8>) -> String {
-    let path = Path::new(MEDIA_PATH)
-        .join(file_name)
-        .with_extension(file_ending);
-    println!("writing file {} to: {}", file_name, path.display());
-    tokio::fs::write(&path, data)
-        .await
-        .map_err(|e| {
-            eprintln!("error writing file: {}", e);
-            warp::reject::reject()
+/// Lock on the resource directory, held by the service while it runs so
+/// admin commands don't change the store behind its back.
+pub fn lock_store() -> io::Result<File> {
+    let file = File::create(Path::new(RESOURCE_PATH).join(".lock"))?;
+    file.try_lock()?;
+    Ok(file)
+}
+
+/// Display name for an uploaded file, without any directories or extension.
+pub fn sanitize_file_name(file_name: &str) -> String {
+    let base_name = file_name.rsplit(['/', '\\']).next().unwrap_or_default();
+    let stem = Path::new(base_name)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let name = stem
+        .chars()
+        .filter(|c| !c.is_control())
+        .collect::<String>()
+        .trim()
+        .to_string();
+    if name.is_empty() || name.chars().all(|c| c == '.') {
+        "untitled".to_string()
+    } else {
+        name
+    }
+}
+
+/// Encodes everything but unreserved characters, for query strings and
+/// `filename*` parameters.
+pub fn percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
         })
-        .unwrap();
-    println!("created file: {}", file_name);
-    path.to_string_lossy().to_string()
+        .collect()
 }
 
-pub async fn remove_file(file_locator: &str) {
-    let path = Path::new(file_locator);
-    // delete file
-    println!("deleting file: {}", path.display());
-    tokio::fs::remove_file(&path)
-        .await
-        .map_err(|e| {
-            eprint!("error deleting file: {}", e);
-            warp::reject::reject()
+pub fn hash_file(path: &Path) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+pub fn max_upload_size() -> u64 {
+    std::env::var("MAX_UPLOAD_SIZE")
+        .ok()
+        .and_then(|size| size.parse().ok())
+        .unwrap_or(MAX_UPLOAD_SIZE)
+}
+
+pub fn tts_command() -> String {
+    std::env::var("TTS_COMMAND").unwrap_or_else(|_| TTS_COMMAND.to_string())
+}
+
+pub fn log_level() -> String {
+    std::env::var("LOG_LEVEL").unwrap_or_else(|_| LOG_LEVEL.to_string())
+}
+
+pub fn log_format() -> String {
+    std::env::var("LOG_FORMAT").unwrap_or_else(|_| LOG_FORMAT.to_string())
+}
+
+/// Logs to journald instead of stdout when `LOG_JOURNALD` is `1` or `true`.
+pub fn log_journald() -> bool {
+    std::env::var("LOG_JOURNALD").is_ok_and(|value| value == "1" || value == "true")
+}
+
+/// Keeps the library in sync with the media directory while running when
+/// `WATCH_MEDIA` is `1` or `true`, instead of only on startup.
+pub fn watch_media() -> bool {
+    std::env::var("WATCH_MEDIA").is_ok_and(|value| value == "1" || value == "true")
+}
+
+/// Directory to serve the web UI from instead of the embedded copy, set with
+/// `WEB_DIR` while working on the UI.
+pub fn web_dir() -> Option<String> {
+    std::env::var("WEB_DIR").ok().filter(|dir| !dir.is_empty())
+}
+
+/// Time zone schedules run in: `TIME_ZONE`, e.g. `Europe/Berlin`, or the system's.
+pub fn time_zone() -> Tz {
+    static TIME_ZONE: OnceLock<Tz> = OnceLock::new();
+    *TIME_ZONE.get_or_init(|| {
+        let name = std::env::var("TIME_ZONE")
+            .ok()
+            .filter(|zone| !zone.is_empty())
+            .or_else(|| iana_time_zone::get_timezone().ok());
+        let Some(name) = name else {
+            warn!("could not determine the time zone, using UTC");
+            return Tz::UTC;
+        };
+        name.parse().unwrap_or_else(|e| {
+            warn!("unknown time zone {}, using UTC: {}", name, e);
+            Tz::UTC
         })
-        .unwrap();
-    println!("deleted file: {}", file_locator);
+    })
+}
+
+pub fn zones() -> String {
+    std::env::var("ZONES").unwrap_or_else(|_| ZONES.to_string())
+}
+
+pub fn target_loudness() -> f64 {
+    std::env::var("TARGET_LOUDNESS")
+        .ok()
+        .and_then(|loudness| loudness.parse().ok())
+        .unwrap_or(TARGET_LOUDNESS)
+}
+
+/// Deletes a file from disk, one that is already gone counts as deleted.
+pub async fn remove_file(file_locator: &str) -> io::Result<()> {
+    let path = Path::new(file_locator);
+    info!("deleting file: {}", path.display());
+    match tokio::fs::remove_file(&path).await {
+        Ok(()) => info!("deleted file: {}", file_locator),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            warn!("file was already gone: {}", file_locator)
+        }
+        Err(e) => return Err(e),
+    }
+    Ok(())
+}
+
+/// Gives a test an empty store under `RESOURCE_PATH` and `MEDIA_PATH` to itself until the guard drops.
+#[cfg(test)]
+pub async fn test_store() -> tokio::sync::MutexGuard<'static, ()> {
+    static STORE: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+    let guard = STORE.lock().await;
+    for dir in [RESOURCE_PATH, crate::consts::MEDIA_PATH] {
+        let _ = std::fs::remove_dir_all(dir);
+        std::fs::create_dir_all(dir).unwrap();
+    }
+    guard
 }
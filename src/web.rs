@@ -0,0 +1,56 @@
+/// A file of `static/`, embedded by `build.rs`.
+pub struct Asset {
+    pub path: &'static str,
+    pub content_type: &'static str,
+    pub etag: &'static str,
+    pub raw: &'static [u8],
+    pub gzip: Option<&'static [u8]>,
+    pub brotli: Option<&'static [u8]>,
+}
+
+include!(concat!(env!("OUT_DIR"), "/assets.rs"));
+
+/// Asset at a request path, directories resolve to their `index.html`.
+pub fn find(path: &str) -> Option<&'static Asset> {
+    let path = path.trim_start_matches('/');
+    let lookup = |path: &str| ASSETS.iter().find(|asset| asset.path == path);
+    if path.is_empty() || path.ends_with('/') {
+        return lookup(&format!("{}index.html", path));
+    }
+    lookup(path).or_else(|| lookup(&format!("{}/index.html", path)))
+}
+
+impl Asset {
+    /// Smallest encoding the client accepts, with its `content-encoding`.
+    pub fn encode(&self, accept_encoding: Option<&str>) -> (&'static [u8], Option<&'static str>) {
+        let accepted =
+            |encoding: &str| accept_encoding.is_some_and(|header| accepts(header, encoding));
+        match (self.brotli, self.gzip) {
+            (Some(brotli), _) if accepted("br") => (brotli, Some("br")),
+            (_, Some(gzip)) if accepted("gzip") => (gzip, Some("gzip")),
+            _ => (self.raw, None),
+        }
+    }
+
+    /// Whether an `if-none-match` header lists this asset's ETag.
+    pub fn matches(&self, if_none_match: &str) -> bool {
+        if_none_match
+            .split(',')
+            .map(|tag| tag.trim().trim_start_matches("W/"))
+            .any(|tag| tag == "*" || tag == self.etag)
+    }
+}
+
+fn accepts(accept_encoding: &str, encoding: &str) -> bool {
+    accept_encoding.split(',').any(|item| {
+        let mut parts = item.split(';').map(str::trim);
+        let name = parts.next().unwrap_or_default();
+        let rejected = parts.any(|param| {
+            param
+                .strip_prefix("q=")
+                .and_then(|q| q.parse::<f32>().ok())
+                .is_some_and(|q| q == 0.0)
+        });
+        name.eq_ignore_ascii_case(encoding) && !rejected
+    })
+}
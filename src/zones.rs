@@ -0,0 +1,299 @@
+use rodio::cpal::traits::{DeviceTrait, HostTrait};
+use rodio::{OutputStream, OutputStreamHandle, Source};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::{error, info, info_span, warn};
+
+use crate::models::{Status, ZoneStatus};
+use crate::player::Player;
+
+/// How long the output may stop pulling samples before the device is considered lost.
+const HEARTBEAT_TIMEOUT: Duration = Duration::from_secs(3);
+/// Delay between attempts to reopen a missing device.
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Named group of speakers, played through one output device.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct ZoneConfig {
+    pub name: String,
+    /// Output device name as reported by `/devices`, `None` for the system default.
+    pub device: Option<String>,
+}
+
+/// Parses `name=device` pairs separated by `;`, e.g.
+/// `office=default;hallway=USB Audio`. A device of `default` (or nothing)
+/// selects the system default output.
+pub fn parse_zones(config: &str) -> Result<Vec<ZoneConfig>, String> {
+    let mut zones: Vec<ZoneConfig> = vec![];
+    for entry in config
+        .split(';')
+        .map(|e| e.trim())
+        .filter(|e| !e.is_empty())
+    {
+        let (name, device) = entry.split_once('=').unwrap_or((entry, ""));
+        let name = name.trim();
+        let device = device.trim();
+        if name.is_empty() {
+            return Err(format!("zone without a name: '{}'", entry));
+        }
+        if zones.iter().any(|z| z.name == name) {
+            return Err(format!("zone {} is defined twice", name));
+        }
+        zones.push(ZoneConfig {
+            name: name.to_string(),
+            device: match device {
+                "" | "default" => None,
+                device => Some(device.to_string()),
+            },
+        });
+    }
+    if zones.is_empty() {
+        return Err("no zones configured".to_string());
+    }
+    Ok(zones)
+}
+
+/// Names of all output devices of the default audio host.
+pub fn output_devices() -> Vec<String> {
+    let host = rodio::cpal::default_host();
+    match host.output_devices() {
+        Ok(devices) => devices.filter_map(|d| d.name().ok()).collect(),
+        Err(e) => {
+            warn!("error enumerating output devices: {}", e);
+            vec![]
+        }
+    }
+}
+
+/// Opens the device of a zone. Devices are matched by exact name first,
+/// then by a case insensitive substring so `USB` finds `USB Audio [hw:1,0]`.
+pub fn open_output(device: Option<&str>) -> Result<(OutputStream, OutputStreamHandle), String> {
+    let Some(name) = device else {
+        return OutputStream::try_default().map_err(|e| e.to_string());
+    };
+    let host = rodio::cpal::default_host();
+    let devices = host
+        .output_devices()
+        .map_err(|e| e.to_string())?
+        .filter_map(|d| d.name().ok().map(|n| (n, d)))
+        .collect::<Vec<_>>();
+    let lowercase = name.to_lowercase();
+    let (_, found) = devices
+        .iter()
+        .find(|(n, _)| n == name)
+        .or_else(|| {
+            devices
+                .iter()
+                .find(|(n, _)| n.to_lowercase().contains(&lowercase))
+        })
+        .ok_or(format!("output device {} not found", name))?;
+    OutputStream::try_from_device(found).map_err(|e| e.to_string())
+}
+
+/// Silent source mixed into the output next to the sink. The device pulls
+/// it like any other sound, so when it stops advancing the device is gone.
+struct Heartbeat {
+    started: Instant,
+    last_beat: Arc<AtomicU64>,
+    samples: u32,
+}
+
+impl Iterator for Heartbeat {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        self.samples += 1;
+        if self.samples == 4410 {
+            self.samples = 0;
+            let elapsed = self.started.elapsed().as_millis() as u64;
+            self.last_beat.store(elapsed, Ordering::Relaxed);
+        }
+        Some(0.0)
+    }
+}
+
+impl Source for Heartbeat {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        1
+    }
+
+    fn sample_rate(&self) -> u32 {
+        44100
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+/// Keeps the device of a zone open, reopening it whenever it disappears.
+/// Runs on its own thread since the output stream cannot leave the thread
+/// that opened it.
+fn supervise(player: Arc<Player>) {
+    let zone = player.zone().clone();
+    let _span = info_span!("zone", zone = %zone.name).entered();
+    let device = zone.device.as_deref().unwrap_or("default device");
+    loop {
+        info!("Opening zone {} on {}", zone.name, device);
+        let opened = open_output(zone.device.as_deref()).and_then(|(stream, stream_handle)| {
+            let started = Instant::now();
+            let last_beat = Arc::new(AtomicU64::new(0));
+            let heartbeat = Heartbeat {
+                started,
+                last_beat: last_beat.clone(),
+                samples: 0,
+            };
+            stream_handle
+                .play_raw(heartbeat)
+                .map_err(|e| e.to_string())?;
+            player.connect(&stream_handle)?;
+            Ok((stream, started, last_beat))
+        });
+        match opened {
+            Ok((stream, started, last_beat)) => {
+                info!("Zone {} connected", zone.name);
+                loop {
+                    thread::sleep(Duration::from_secs(1));
+                    let last_beat = Duration::from_millis(last_beat.load(Ordering::Relaxed));
+                    if started.elapsed().saturating_sub(last_beat) > HEARTBEAT_TIMEOUT {
+                        break;
+                    }
+                }
+                error!("Zone {} lost its output device {}", zone.name, device);
+                player.disconnect();
+                drop(stream);
+            }
+            Err(e) => error!("failed to open zone {}: {}", zone.name, e),
+        }
+        thread::sleep(RECONNECT_INTERVAL);
+    }
+}
+
+/// One player per configured zone, the first zone being the default.
+pub struct Zones {
+    players: Vec<Arc<Player>>,
+}
+
+impl Zones {
+    /// Starts a player for every zone. Zones whose device is missing report
+    /// `Disconnected` until it shows up.
+    pub fn open(configs: Vec<ZoneConfig>, target_loudness: f64) -> Zones {
+        let mut players = vec![];
+        for config in configs {
+            let player = Arc::new(Player::new(config, target_loudness));
+            let supervised = player.clone();
+            thread::Builder::new()
+                .name(format!("zone-{}", player.zone().name))
+                .spawn(move || supervise(supervised))
+                .unwrap();
+            players.push(player);
+        }
+        Zones { players }
+    }
+
+    /// Player of the named zone, or of the default zone if `zone` is `None`.
+    pub fn get(&self, zone: Option<&str>) -> Option<&Player> {
+        match zone {
+            Some(zone) => self.players.iter().find(|p| p.zone().name == zone),
+            None => self.players.first(),
+        }
+        .map(|p| p.as_ref())
+    }
+
+    /// Players of the named zone, or of all zones if `zone` is `None`.
+    pub fn select(&self, zone: Option<&str>) -> Option<Vec<&Player>> {
+        match zone {
+            Some(_) => self.get(zone).map(|p| vec![p]),
+            None => Some(self.players.iter().map(|p| p.as_ref()).collect()),
+        }
+    }
+
+    pub fn players(&self) -> impl Iterator<Item = &Player> {
+        self.players.iter().map(|p| p.as_ref())
+    }
+
+    /// Ramps every zone down to silence and stops it, then restores the
+    /// volume so whatever plays next isn't silent.
+    pub async fn fade_out(&self, duration: Duration) {
+        const STEPS: u32 = 20;
+        if self.players().all(|p| p.done()) {
+            return;
+        }
+        for step in (0..STEPS).rev() {
+            for player in self.players() {
+                player.set_volume(step as f32 / STEPS as f32);
+            }
+            tokio::time::sleep(duration / STEPS).await;
+        }
+        for player in self.players() {
+            player.stop();
+            player.set_volume(1.0);
+        }
+    }
+
+    pub fn connected(&self) -> bool {
+        self.players
+            .iter()
+            .all(|p| p.status() != Status::Disconnected)
+    }
+
+    pub fn status(&self) -> Vec<ZoneStatus> {
+        self.players.iter().map(|p| p.zone_status()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_zones() {
+        assert_eq!(
+            parse_zones("office=default; hallway = USB Audio ;garden").unwrap(),
+            vec![
+                ZoneConfig {
+                    name: "office".to_string(),
+                    device: None,
+                },
+                ZoneConfig {
+                    name: "hallway".to_string(),
+                    device: Some("USB Audio".to_string()),
+                },
+                ZoneConfig {
+                    name: "garden".to_string(),
+                    device: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_empty_lists() {
+        assert!(parse_zones("").is_err());
+        assert!(parse_zones(" ; ;").is_err());
+        assert!(parse_zones("=USB Audio").is_err());
+    }
+
+    #[test]
+    fn rejects_duplicate_names() {
+        assert_eq!(
+            parse_zones("office=default;office=USB Audio"),
+            Err("zone office is defined twice".to_string())
+        );
+    }
+
+    #[test]
+    fn unknown_devices_fail_to_open() {
+        // devices are only looked up when the zone opens, so it can show up later
+        let zones = parse_zones("office=No Such Device").unwrap();
+        assert_eq!(zones[0].device.as_deref(), Some("No Such Device"));
+        assert!(open_output(zones[0].device.as_deref()).is_err());
+    }
+}
@@ -0,0 +1,565 @@
+"use strict";
+
+const API = "/api/v1";
+const STATUS_INTERVAL = 2000;
+
+let files = [];
+let schedules = [];
+let zones = [];
+
+// --- helpers ---------------------------------------------------------------
+
+function el(tag, attributes = {}, ...children) {
+    const element = document.createElement(tag);
+    for (const [name, value] of Object.entries(attributes)) {
+        if (name.startsWith("on")) {
+            element.addEventListener(name.slice(2), value);
+        } else if (typeof value === "boolean") {
+            element[name] = value;
+        } else if (value !== undefined && value !== null) {
+            element.setAttribute(name, value);
+        }
+    }
+    for (const child of children.flat()) {
+        if (child !== undefined && child !== null) {
+            element.append(child);
+        }
+    }
+    return element;
+}
+
+async function api(method, path, body) {
+    const options = { method, headers: {} };
+    if (body !== undefined) {
+        options.headers["content-type"] = "application/json";
+        options.body = JSON.stringify(body);
+    }
+    const response = await fetch(API + path, options);
+    if (!response.ok) {
+        const error = new Error(await errorMessage(response));
+        error.status = response.status;
+        throw error;
+    }
+    return response.status === 204 ? null : response.json();
+}
+
+async function errorMessage(response) {
+    try {
+        return (await response.json()).message;
+    } catch {
+        return `${response.status} ${response.statusText}`;
+    }
+}
+
+function showMessage(text, isError = true) {
+    const message = document.getElementById("message");
+    message.textContent = text;
+    message.classList.toggle("error", isError);
+    message.hidden = false;
+    clearTimeout(showMessage.timeout);
+    showMessage.timeout = setTimeout(() => (message.hidden = true), 5000);
+}
+
+// runs an action, reporting failures instead of leaving them in the console
+function attempt(action) {
+    return async (...args) => {
+        try {
+            await action(...args);
+        } catch (e) {
+            showMessage(e.message);
+        }
+    };
+}
+
+function formatDuration(seconds) {
+    if (seconds === null || seconds === undefined) {
+        return "";
+    }
+    const minutes = Math.floor(seconds / 60);
+    return `${minutes}:${String(Math.round(seconds % 60)).padStart(2, "0")}`;
+}
+
+function fileName(id) {
+    const file = files.find((f) => f.id === id);
+    return file ? file.name : `#${id} (missing)`;
+}
+
+function kindLabel(file) {
+    if (!file.kind) {
+        return null;
+    }
+    if (file.kind.Stream) {
+        return "stream";
+    }
+    return "generator";
+}
+
+function zoneSelect(name, includeDefault, selected) {
+    const select = el("select", { name });
+    if (includeDefault) {
+        select.append(el("option", { value: "" }, "default"));
+    }
+    for (const zone of zones) {
+        select.append(el("option", { value: zone.zone, selected: zone.zone === selected }, zone.zone));
+    }
+    return select;
+}
+
+// --- status ------------------------------------------------------------------
+
+async function refreshStatus() {
+    try {
+        const status = await api("GET", "/status");
+        zones = status.zones;
+        renderZones();
+    } catch (e) {
+        document.getElementById("zones").replaceChildren(el("p", { class: "error" }, `Status unavailable: ${e.message}`));
+    }
+}
+
+function renderZones() {
+    const container = document.getElementById("zones");
+    container.replaceChildren(
+        ...zones.map((zone) =>
+            el(
+                "div",
+                { class: `zone status-${zone.status.toLowerCase()}` },
+                el("h3", {}, zone.zone),
+                el("p", { class: "device" }, zone.device || "default device"),
+                el("p", { class: "state" }, zone.status),
+                zone.stream ? el("p", { class: "stream" }, zone.stream.title || zone.stream.url) : null,
+                el(
+                    "div",
+                    { class: "buttons" },
+                    ["pause", "resume", "stop"].map((action) =>
+                        el("button", { onclick: attempt(() => control(action, zone.zone)) }, action)
+                    )
+                )
+            )
+        )
+    );
+}
+
+async function control(action, zone) {
+    await api("POST", `/playback/${action}?zone=${encodeURIComponent(zone)}`);
+    await refreshStatus();
+}
+
+// --- files -------------------------------------------------------------------
+
+async function refreshFiles() {
+    files = await api("GET", "/files");
+    renderFiles();
+    renderFileSelects();
+}
+
+function renderFiles() {
+    const tbody = document.getElementById("files");
+    tbody.replaceChildren(
+        ...files.map((file) => {
+            const zone = zoneSelect("zone", false);
+            const metadata = file.metadata || {};
+            const loudness = metadata.loudness;
+            return el(
+                "tr",
+                {},
+                el(
+                    "td",
+                    {},
+                    file.name,
+                    kindLabel(file) ? el("span", { class: "tag" }, kindLabel(file)) : null,
+                    file.missing ? el("span", { class: "tag missing", title: file.path }, "missing") : null,
+                    file.source_id !== undefined ? el("span", { class: "tag" }, `from ${fileName(file.source_id)}`) : null
+                ),
+                el("td", {}, formatDuration(metadata.duration)),
+                el("td", {}, loudness !== null && loudness !== undefined ? `${loudness.toFixed(1)} LUFS` : ""),
+                el(
+                    "td",
+                    {},
+                    el("input", {
+                        type: "checkbox",
+                        checked: file.normalize,
+                        onchange: attempt(async (event) => {
+                            await api("PATCH", `/files/${file.id}`, { normalize: event.target.checked });
+                            await refreshFiles();
+                        }),
+                    })
+                ),
+                el("td", {}, zone),
+                el(
+                    "td",
+                    { class: "buttons" },
+                    el("button", { onclick: attempt(() => play(file, zone.value)) }, "Play"),
+                    !kindLabel(file) ? el("a", { class: "button", href: `${API}/files/${file.id}/content`, download: "" }, "Download") : null,
+                    el("button", { class: "danger", onclick: attempt(() => deleteFile(file)) }, "Delete")
+                )
+            );
+        })
+    );
+}
+
+async function play(file, zone) {
+    await api("POST", "/playback", { file_id: file.id, zone });
+    await refreshStatus();
+}
+
+function usedBy(fileId) {
+    return schedules.filter((s) => [s.file_id, s.pre_roll, s.post_roll].includes(fileId));
+}
+
+async function deleteFile(file) {
+    let dependents = usedBy(file.id);
+    if (!dependents.length) {
+        if (!confirm(`Delete ${file.name}?`)) {
+            return;
+        }
+        try {
+            await api("DELETE", `/files/${file.id}`);
+            await refreshFiles();
+            return;
+        } catch (e) {
+            // a schedule started using it since the list was loaded
+            if (e.status !== 409) {
+                throw e;
+            }
+        }
+        await refreshSchedules();
+        dependents = usedBy(file.id);
+    }
+    const query = await chooseDeletion(file, dependents);
+    if (query === null) {
+        return;
+    }
+    await api("DELETE", `/files/${file.id}?${query}`);
+    await Promise.all([refreshFiles(), refreshSchedules()]);
+}
+
+// asks what happens to the schedules using a file, resolves to the query to delete it with or null
+function chooseDeletion(file, dependents) {
+    const dialog = document.getElementById("delete-dialog");
+    const form = dialog.querySelector("form");
+    form.reset();
+    dialog.querySelector("h3").textContent = `Delete ${file.name}?`;
+    dialog.querySelector(".dependents").replaceChildren(
+        ...dependents.map((schedule) => {
+            const role = schedule.file_id === file.id ? null : schedule.pre_roll === file.id ? "pre-roll" : "post-roll";
+            return el(
+                "li",
+                {},
+                `${fileName(schedule.file_id)}, ${describeSchedule(schedule)}`,
+                role ? el("span", { class: "tag" }, role) : null
+            );
+        })
+    );
+    const others = files.filter((f) => f.id !== file.id);
+    form.elements.reassign.replaceChildren(...others.map((f) => el("option", { value: f.id }, f.name)));
+    form.querySelector('input[value="reassign"]').disabled = !others.length;
+    dialog.returnValue = "";
+    dialog.showModal();
+    return new Promise((resolve) => {
+        dialog.addEventListener(
+            "close",
+            () => {
+                if (dialog.returnValue !== "delete") {
+                    resolve(null);
+                    return;
+                }
+                const action = form.elements.action.value;
+                resolve(action === "reassign" ? `reassign=${form.elements.reassign.value}` : `${action}=true`);
+            },
+            { once: true }
+        );
+    });
+}
+
+// --- uploads -----------------------------------------------------------------
+
+function upload(file) {
+    const conflict = document.getElementById("conflict").value;
+    const progress = el("progress", { max: 100, value: 0 });
+    const item = el("li", {}, file.name, " ", progress);
+    document.getElementById("uploads").append(item);
+
+    const form = new FormData();
+    form.append("file", file);
+    const request = new XMLHttpRequest();
+    request.open("POST", `${API}/files?conflict=${conflict}`);
+    request.upload.addEventListener("progress", (event) => {
+        if (event.lengthComputable) {
+            progress.value = (event.loaded / event.total) * 100;
+        }
+    });
+    request.addEventListener("load", () => {
+        item.remove();
+        if (request.status === 201) {
+            showMessage(`Uploaded ${file.name}`, false);
+            refreshFiles().catch((e) => showMessage(e.message));
+        } else {
+            let message = `${request.status} ${request.statusText}`;
+            try {
+                message = JSON.parse(request.responseText).message;
+            } catch {
+                // not an API error body, keep the status line
+            }
+            showMessage(`${file.name}: ${message}`);
+        }
+    });
+    request.addEventListener("error", () => {
+        item.remove();
+        showMessage(`${file.name}: upload failed`);
+    });
+    request.send(form);
+}
+
+function setupUploads() {
+    const dropzone = document.getElementById("dropzone");
+    dropzone.addEventListener("dragover", (event) => {
+        event.preventDefault();
+        dropzone.classList.add("dragging");
+    });
+    dropzone.addEventListener("dragleave", () => dropzone.classList.remove("dragging"));
+    dropzone.addEventListener("drop", (event) => {
+        event.preventDefault();
+        dropzone.classList.remove("dragging");
+        [...event.dataTransfer.files].forEach(upload);
+    });
+    const input = document.getElementById("file-input");
+    input.addEventListener("change", () => {
+        [...input.files].forEach(upload);
+        input.value = "";
+    });
+    document.getElementById("scan").addEventListener(
+        "click",
+        attempt(async () => {
+            const result = await api("POST", "/files/scan");
+            await refreshFiles();
+            showMessage(`${result.added.length} files added, ${result.missing.length} missing`, result.missing.length > 0);
+        })
+    );
+}
+
+// --- schedules ---------------------------------------------------------------
+
+async function refreshSchedules() {
+    schedules = await api("GET", "/schedules");
+    renderSchedules();
+}
+
+function describeSchedule(schedule) {
+    const spec = schedule.spec;
+    if (!spec) {
+        return schedule.schedule;
+    }
+    const days = spec.days.length ? spec.days.join(", ") : "every day";
+    const parts = [...spec.times];
+    if (spec.every) {
+        parts.push(`every ${spec.every.minutes} min ${spec.every.from}–${spec.every.to}`);
+    }
+    return `${days}: ${parts.join(", ")}`;
+}
+
+function renderSchedules() {
+    const tbody = document.getElementById("schedules");
+    tbody.replaceChildren(
+        ...schedules.map((schedule) => {
+            const rolls = [schedule.pre_roll, schedule.post_roll].map((id) => (id === undefined ? "–" : fileName(id)));
+            return el(
+                "tr",
+                {},
+                el("td", {}, fileName(schedule.file_id)),
+                el("td", { title: schedule.schedule }, describeSchedule(schedule)),
+                el("td", {}, schedule.zone || "default"),
+                el("td", {}, rolls.join(" / ")),
+                el(
+                    "td",
+                    {},
+                    el("input", {
+                        type: "checkbox",
+                        checked: schedule.activity === "Active",
+                        onchange: attempt(async (event) => {
+                            await api("PATCH", `/schedules/${schedule.id}`, { active: event.target.checked });
+                            await refreshSchedules();
+                        }),
+                    })
+                ),
+                el(
+                    "td",
+                    { class: "buttons" },
+                    el("button", { onclick: () => editSchedule(schedule) }, "Edit"),
+                    el("button", { class: "danger", onclick: attempt(() => deleteSchedule(schedule)) }, "Delete")
+                )
+            );
+        })
+    );
+}
+
+async function deleteSchedule(schedule) {
+    if (!confirm(`Delete the schedule of ${fileName(schedule.file_id)}?`)) {
+        return;
+    }
+    await api("DELETE", `/schedules/${schedule.id}`);
+    await refreshSchedules();
+}
+
+function renderFileSelects() {
+    const form = document.getElementById("schedule-form");
+    for (const name of ["file_id", "pre_roll", "post_roll"]) {
+        const select = form.elements[name];
+        const selected = select.value;
+        const options = files.map((file) => el("option", { value: file.id }, file.name));
+        if (name !== "file_id") {
+            options.unshift(el("option", { value: "" }, "none"));
+        }
+        select.replaceChildren(...options);
+        select.value = selected;
+    }
+    const zone = form.elements.zone;
+    const selectedZone = zone.value;
+    zone.replaceChildren(...zoneSelect("zone", true).children);
+    zone.value = selectedZone;
+}
+
+function setMode(mode) {
+    const form = document.getElementById("schedule-form");
+    form.elements.mode.value = mode;
+    form.querySelector(".mode-spec").hidden = mode !== "spec";
+    form.querySelector(".mode-cron").hidden = mode !== "cron";
+}
+
+function resetScheduleForm() {
+    const form = document.getElementById("schedule-form");
+    form.reset();
+    form.elements.id.value = "";
+    document.getElementById("schedule-form-title").textContent = "New schedule";
+    form.querySelector(".form-error").hidden = true;
+    setMode("spec");
+}
+
+function editSchedule(schedule) {
+    resetScheduleForm();
+    const form = document.getElementById("schedule-form");
+    document.getElementById("schedule-form-title").textContent = `Edit schedule ${schedule.id}`;
+    form.elements.id.value = schedule.id;
+    form.elements.file_id.value = schedule.file_id;
+    form.elements.pre_roll.value = schedule.pre_roll ?? "";
+    form.elements.post_roll.value = schedule.post_roll ?? "";
+    form.elements.zone.value = schedule.zone ?? "";
+    form.elements.active.checked = schedule.activity === "Active";
+    if (schedule.spec) {
+        setMode("spec");
+        for (const day of form.elements.days) {
+            day.checked = schedule.spec.days.includes(day.value);
+        }
+        form.elements.times.value = schedule.spec.times.join(", ");
+        if (schedule.spec.every) {
+            form.elements.every_minutes.value = schedule.spec.every.minutes;
+            form.elements.every_from.value = schedule.spec.every.from;
+            form.elements.every_to.value = schedule.spec.every.to;
+        }
+    } else {
+        setMode("cron");
+        form.elements.cron.value = schedule.schedule;
+    }
+    form.scrollIntoView({ behavior: "smooth" });
+}
+
+function optionalId(value) {
+    return value === "" ? null : Number(value);
+}
+
+// checks what the browser can, the server validates the rest
+function readScheduleForm(form) {
+    const elements = form.elements;
+    if (elements.file_id.value === "") {
+        throw new Error("Choose a file");
+    }
+    let schedule;
+    if (elements.mode.value === "cron") {
+        schedule = elements.cron.value.trim();
+        if (!schedule) {
+            throw new Error("Enter a cron expression");
+        }
+    } else {
+        const times = elements.times.value
+            .split(",")
+            .map((time) => time.trim())
+            .filter((time) => time);
+        const invalid = times.find((time) => !/^\d{1,2}:\d{2}(:\d{2})?$/.test(time));
+        if (invalid) {
+            throw new Error(`Invalid time ${invalid}, use HH:MM`);
+        }
+        let every = null;
+        if (elements.every_minutes.value) {
+            every = {
+                minutes: Number(elements.every_minutes.value),
+                from: elements.every_from.value.trim(),
+                to: elements.every_to.value.trim(),
+            };
+            if (!every.from || !every.to) {
+                throw new Error("An interval needs a start and an end time");
+            }
+        }
+        if (!times.length && !every) {
+            throw new Error("Enter at least one time or an interval");
+        }
+        schedule = {
+            days: [...elements.days].filter((day) => day.checked).map((day) => day.value),
+            times,
+            every,
+        };
+    }
+    return {
+        file_id: Number(elements.file_id.value),
+        schedule,
+        pre_roll: optionalId(elements.pre_roll.value),
+        post_roll: optionalId(elements.post_roll.value),
+        zone: elements.zone.value || null,
+        active: elements.active.checked,
+    };
+}
+
+async function submitSchedule(event) {
+    event.preventDefault();
+    const form = event.target;
+    const error = form.querySelector(".form-error");
+    error.hidden = true;
+    try {
+        const request = readScheduleForm(form);
+        const id = form.elements.id.value;
+        if (id) {
+            await api("PUT", `/schedules/${id}`, request);
+        } else {
+            await api("POST", "/schedules", request);
+        }
+        resetScheduleForm();
+        await refreshSchedules();
+        showMessage("Schedule saved", false);
+    } catch (e) {
+        error.textContent = e.message;
+        error.hidden = false;
+    }
+}
+
+function setupScheduleForm() {
+    const form = document.getElementById("schedule-form");
+    form.addEventListener("submit", submitSchedule);
+    for (const radio of form.elements.mode) {
+        radio.addEventListener("change", () => setMode(radio.value));
+    }
+    document.getElementById("schedule-cancel").addEventListener("click", resetScheduleForm);
+}
+
+// --- start -------------------------------------------------------------------
+
+async function start() {
+    setupUploads();
+    setupScheduleForm();
+    await refreshStatus();
+    await attempt(async () => {
+        await refreshFiles();
+        await refreshSchedules();
+    })();
+    setInterval(refreshStatus, STATUS_INTERVAL);
+}
+
+start();